@@ -1,53 +1,130 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, TokenAccount, Token, Mint};
-use anchor_lang::solana_program::{sysvar, clock, program_option::COption};
+use std::convert::TryInto;
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface, Mint};
+use anchor_spl::token_2022::{self, spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions, transfer_fee::TransferFeeConfig}};
+use anchor_spl::token::{Token, spl_token::native_mint};
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::metadata::{self, Metadata, MetadataAccount};
+use anchor_spl::metadata::mpl_token_metadata::{instructions::{TransferV1CpiBuilder, CreateMetadataAccountV3CpiBuilder, CreateMasterEditionV3CpiBuilder}, types::{TokenStandard, DataV2}};
+use anchor_lang::solana_program::{clock, system_instruction, sysvar, ed25519_program, program_option::COption};
+use anchor_lang::solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
 
 declare_id!("DuPw7Lsvkr9XM5H3nv8733eCznT7hBWYjCkb1UV9YYex");
 
+pub const BPS_DENOMINATOR: u64 = 10_000;
+// Length of the collateral auction of a defaulted syndicated loan.
+pub const AUCTION_PERIOD: u64 = 86_400;
+pub const SECONDS_PER_YEAR: u64 = 31_536_000;
+// Virtual shares and assets of every pool tranche, so the first depositor can't inflate the share price.
+pub const VIRTUAL_LIQUIDITY: u64 = 1_000;
+// Token Metadata name and symbol of the promissory notes.
+pub const NOTE_NAME: &str = "Loan Promissory Note";
+pub const NOTE_SYMBOL: &str = "NOTE";
+
 #[program]
 pub mod nft_collaterized_loans {
     use super::*;
 
     pub fn initialize(ctx: Context<Initialize>, nonce: u8) -> Result<()> {
         let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
-        nft_collaterized_loans.stablecoin_mint = ctx.accounts.stablecoin_mint.key();
-        nft_collaterized_loans.stablecoin_vault = ctx.accounts.stablecoin_vault.key();
+        nft_collaterized_loans.authority = ctx.accounts.authority.key();
         nft_collaterized_loans.order_id = 0;
-        nft_collaterized_loans.total_additional_collateral = 0;
         nft_collaterized_loans.nonce = nonce;
 
         Ok(())
     }
 
-    pub fn create_order(ctx: Context<CreateOrder>, nonce:u8, request_amount: u64, interest: u64, period: u64, additional_collateral: u64) -> Result<()> {
+    pub fn set_appraisal_config(ctx: Context<SetAppraisalConfig>, appraiser: Pubkey, max_ltv_bps: u16, max_appraisal_age: u64) -> Result<()> {
+        if max_ltv_bps as u64 > BPS_DENOMINATOR {
+            return Err(ErrorCode::InvalidLoanToValue.into());
+        }
+
+        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
+        nft_collaterized_loans.appraiser = appraiser;
+        nft_collaterized_loans.max_ltv_bps = max_ltv_bps;
+        nft_collaterized_loans.max_appraisal_age = max_appraisal_age;
+
+        Ok(())
+    }
+
+    pub fn add_currency(ctx: Context<AddCurrency>, nonce: u8) -> Result<()> {
+        let currency = &mut ctx.accounts.currency;
+        currency.market = ctx.accounts.nft_collaterized_loans.key();
+        currency.stablecoin_mint = ctx.accounts.stablecoin_mint.key();
+        currency.stablecoin_vault = ctx.accounts.stablecoin_vault.key();
+        currency.total_additional_collateral = 0;
+        currency.nonce = nonce;
+
+        Ok(())
+    }
+
+    pub fn create_order(ctx: Context<CreateOrder>, nonce:u8, request_amount: u64, interest: u64, period: u64, additional_collateral: u64, expires_at: u64) -> Result<()> {
         if request_amount == 0 {
             return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
         }
 
+        ctx.accounts.nft_collaterized_loans.check_loan_to_value(
+            ctx.accounts.instructions_sysvar.as_ref(),
+            &ctx.accounts.nft_mint.key(),
+            &ctx.accounts.stablecoin_mint.key(),
+            request_amount,
+            additional_collateral,
+        )?;
+
         // Transfer collateral to vault.
-        {
+        let is_programmable = ctx.accounts.pnft.is_programmable(&ctx.accounts.nft_mint.key())?;
+        if is_programmable {
+            ctx.accounts.pnft.transfer(
+                ctx.accounts.user_nft_vault.to_account_info(),
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.nft_vault.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.nft_token_program.to_account_info(),
+                &[],
+            )?;
+        } else {
             let cpi_ctx = CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
                     from: ctx.accounts.user_nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
                     to: ctx.accounts.nft_vault.to_account_info(),
                     authority: ctx.accounts.borrower.to_account_info(), //todo use user account as signer
                 },
             );
-            token::transfer(cpi_ctx, 1)?;
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        ctx.accounts.nft_vault.reload()?;
+        if ctx.accounts.nft_vault.amount != 1 {
+            return Err(ErrorCode::ReceivedAmountMismatch.into());
         }
 
         // Transfer additional collateral to vault
         {
+            let balance_before = ctx.accounts.stablecoin_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), additional_collateral)?;
+
             let cpi_ctx = CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
+                token_interface::TransferChecked {
                     from: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
                     to: ctx.accounts.stablecoin_vault.to_account_info(),
                     authority: ctx.accounts.borrower.to_account_info(), //todo use user account as signer
                 },
             );
-            token::transfer(cpi_ctx, additional_collateral)?;
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.stablecoin_vault.reload()?;
+            if ctx.accounts.stablecoin_vault.amount.checked_sub(balance_before).unwrap() != additional_collateral {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
         }
 
         let clock = clock::Clock::get().unwrap();
@@ -56,6 +133,7 @@ pub mod nft_collaterized_loans {
         let order = &mut ctx.accounts.order;
         order.borrower = ctx.accounts.borrower.key();
         order.stablecoin_vault = ctx.accounts.user_stablecoin_vault.key();
+        order.stablecoin_mint = ctx.accounts.stablecoin_mint.key();
         order.nft_mint = ctx.accounts.nft_mint.key();
         order.nft_vault = ctx.accounts.nft_vault.key();
         order.request_amount = request_amount;
@@ -68,10 +146,13 @@ pub mod nft_collaterized_loans {
         order.paid_back_at = 0;
         order.withdrew_at = 0;
         order.nonce = nonce;
+        order.expires_at = expires_at;
+        order.is_programmable = is_programmable;
 
-        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
-        nft_collaterized_loans.total_additional_collateral += additional_collateral;
+        let currency = &mut ctx.accounts.currency;
+        currency.total_additional_collateral += additional_collateral;
 
+        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
         nft_collaterized_loans.order_id += 1;
 
         order.order_status = true;
@@ -79,29 +160,47 @@ pub mod nft_collaterized_loans {
         Ok(())
     }
 
-    pub fn cancel_order(ctx: Context<CancelOrder>, order_id: u64) -> Result<()> {
+    pub fn cancel_order(ctx: Context<CancelOrder>, _order_id: u64) -> Result<()> {
         let order = &mut ctx.accounts.order;
-        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
 
-        if order.loan_start_time != 0 && order.order_status == false {
+        if order.loan_start_time != 0 && !order.order_status {
             return Err(ErrorCode::LoanAlreadyStarted.into());
         }
         
         // Transfer back nft collateral.
-        {
+        if order.is_programmable {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            ctx.accounts.pnft.transfer(
+                ctx.accounts.nft_vault.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.user_nft_vault.to_account_info(),
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.nft_token_program.to_account_info(),
+                signer,
+            )?;
+        } else {
             let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
             let signer = &[&seeds[..]];
 
             let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
                     from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
                     to: ctx.accounts.user_nft_vault.to_account_info(),
                     authority: ctx.accounts.signer.to_account_info(), 
                 },
                 signer
             );
-            token::transfer(cpi_ctx, 1)?;
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
         }
 
         // Transfer back additional collateral 
@@ -111,16 +210,17 @@ pub mod nft_collaterized_loans {
 
             let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
+                token_interface::TransferChecked {
                     from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
                     to: ctx.accounts.user_stablecoin_vault.to_account_info(),
                     authority: ctx.accounts.signer.to_account_info(), 
                 },
                 signer
             );
-            token::transfer(cpi_ctx, order.additional_collateral)?;
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
         }
-        nft_collaterized_loans.total_additional_collateral -= order.additional_collateral;
+        currency.total_additional_collateral -= order.additional_collateral;
 
         order.order_status = false;
 
@@ -129,44 +229,108 @@ pub mod nft_collaterized_loans {
         Ok(())
     }
 
-    pub fn give_loan(ctx: Context<GiveLoan>, order_id: u64) -> Result<()> {
+    pub fn give_loan(ctx: Context<GiveLoan>, _order_id: u64, request_amount: u64, interest: u64, period: u64) -> Result<()> {
         let order = &mut ctx.accounts.order;
 
-        if order.loan_start_time != 0 && order.order_status == false {
+        if (order.loan_start_time != 0 && !order.order_status) || order.funded_amount != 0 {
             return Err(ErrorCode::LoanAlreadyStarted.into());
         }
 
-        // Transfer back additional collateral 
+        // Terms the lender agreed to must still hold.
+        if order.request_amount != request_amount || order.interest != interest || order.period != period {
+            return Err(ErrorCode::OrderTermsChanged.into());
+        }
+
+        if order.is_private {
+            match &ctx.accounts.allowlist {
+                Some(allowlist) if allowlist.lenders.contains(ctx.accounts.lender.key) => {}
+                _ => return Err(ErrorCode::LenderNotAllowed.into()),
+            }
+        }
+
+        if order.is_expired(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::OrderExpired.into());
+        }
+
+        ctx.accounts.nft_collaterized_loans.check_loan_to_value(
+            ctx.accounts.instructions_sysvar.as_ref(),
+            &order.nft_mint,
+            &order.stablecoin_mint,
+            order.request_amount,
+            order.additional_collateral,
+        )?;
+
+        // Transfer loan to the borrower
         {
+            let balance_before = ctx.accounts.borrower_stablecoin_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), order.request_amount)?;
+
             let cpi_ctx = CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
+                token_interface::TransferChecked {
                     from: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
                     to: ctx.accounts.borrower_stablecoin_vault.to_account_info(),
                     authority: ctx.accounts.lender.to_account_info(), 
                 },
             );
-            token::transfer(cpi_ctx, order.request_amount)?;
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.borrower_stablecoin_vault.reload()?;
+            if ctx.accounts.borrower_stablecoin_vault.amount.checked_sub(balance_before).unwrap() != order.request_amount {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Mint the promissory note to the lender
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.note_token_program.to_account_info(),
+                token_interface::MintTo {
+                    mint: ctx.accounts.note_mint.to_account_info(),
+                    to: ctx.accounts.lender_note_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::mint_to(cpi_ctx, 1)?;
+
+            ctx.accounts.note_metadata.create(
+                ctx.accounts.note_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.lender.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.note_token_program.to_account_info(),
+                signer,
+            )?;
         }
 
         // Save Info
         order.lender = ctx.accounts.lender.key();
+        order.note_mint = ctx.accounts.note_mint.key();
         order.loan_start_time = clock::Clock::get().unwrap().unix_timestamp as u64;
         order.order_status = false;
 
         Ok(())
     }
 
-    pub fn payback(ctx: Context<Payback>, order_id: u64) -> Result<()> {
+    pub fn payback(ctx: Context<Payback>, _order_id: u64) -> Result<()> {
         let order = &mut ctx.accounts.order;
-        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
 
-        if order.loan_start_time == 0 && order.order_status == true {
+        if order.loan_start_time == 0 && order.order_status {
             return Err(ErrorCode::LoanNotProvided.into());
         }
 
+        Strategy::check_payout_vault(&ctx.accounts.note_holder, &ctx.accounts.lender_stablecoin_vault.key())?;
+
         let clock = clock::Clock::get().unwrap();
-        if order.loan_start_time.checked_add(order.period).unwrap() < clock.unix_timestamp as u64 {
+        if order.due_at() < clock.unix_timestamp as u64 {
             return Err(ErrorCode::RepaymentPeriodExceeded.into());
         }
         
@@ -175,32 +339,59 @@ pub mod nft_collaterized_loans {
 
         // Pay Loan
         {
+            let repayment = order.request_amount.checked_add(order.interest_due(order.paid_back_at)).unwrap();
+            let balance_before = ctx.accounts.lender_stablecoin_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), repayment)?;
+
             let cpi_ctx = CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.payer_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
                     to: ctx.accounts.lender_stablecoin_vault.to_account_info(),
-                    authority: ctx.accounts.borrower.to_account_info(), 
+                    authority: ctx.accounts.payer.to_account_info(),
                 },
             );
-            token::transfer(cpi_ctx, order.request_amount.checked_add(order.interest).unwrap())?;
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.lender_stablecoin_vault.reload()?;
+            if ctx.accounts.lender_stablecoin_vault.amount.checked_sub(balance_before).unwrap() != repayment {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
         }
 
         // Transfer back nft collateral.
-        {
+        if order.is_programmable {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            ctx.accounts.pnft.transfer(
+                ctx.accounts.nft_vault.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.user_nft_vault.to_account_info(),
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.nft_token_program.to_account_info(),
+                signer,
+            )?;
+        } else {
             let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
             let signer = &[&seeds[..]];
 
             let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
                     from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
                     to: ctx.accounts.user_nft_vault.to_account_info(),
                     authority: ctx.accounts.signer.to_account_info(), 
                 },
                 signer
             );
-            token::transfer(cpi_ctx, 1)?;
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
         }
 
         // Transfer back additional collateral 
@@ -210,32 +401,34 @@ pub mod nft_collaterized_loans {
 
             let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
+                token_interface::TransferChecked {
                     from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
                     to: ctx.accounts.user_stablecoin_vault.to_account_info(),
                     authority: ctx.accounts.signer.to_account_info(), 
                 },
                 signer
             );
-            token::transfer(cpi_ctx, order.additional_collateral)?;
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
         }
-        nft_collaterized_loans.total_additional_collateral -= order.additional_collateral;
+        currency.total_additional_collateral -= order.additional_collateral;
 
         // Sidenote: Preferred to close the account after this
 
         Ok(())
     }
 
-    pub fn liquidate(ctx: Context<Liquidate>, order_id: u64) -> Result<()> {
+    pub fn liquidate(ctx: Context<Liquidate>, _order_id: u64) -> Result<()> {
         let order = &mut ctx.accounts.order;
-        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
 
-        if order.loan_start_time == 0 && order.order_status == true {
+        if order.loan_start_time == 0 && order.order_status {
             return Err(ErrorCode::LoanNotProvided.into());
         }
 
         let clock = clock::Clock::get().unwrap();
-        if order.loan_start_time.checked_add(order.period).unwrap() > clock.unix_timestamp as u64 {
+        if !order.is_liquidatable(clock.unix_timestamp as u64) {
             return Err(ErrorCode::RepaymentPeriodNotExceeded.into());
         }
         
@@ -247,20 +440,37 @@ pub mod nft_collaterized_loans {
         order.withdrew_at = clock.unix_timestamp as u64;
 
         // Transfer nft collateral.
-        {
+        if order.is_programmable {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            ctx.accounts.pnft.transfer(
+                ctx.accounts.nft_vault.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.user_nft_vault.to_account_info(),
+                ctx.accounts.lender.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.lender.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.nft_token_program.to_account_info(),
+                signer,
+            )?;
+        } else {
             let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
             let signer = &[&seeds[..]];
 
             let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
                     from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
                     to: ctx.accounts.user_nft_vault.to_account_info(),
                     authority: ctx.accounts.signer.to_account_info(), 
                 },
                 signer
             );
-            token::transfer(cpi_ctx, 1)?;
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
         }
 
         // Transfer additional collateral 
@@ -270,200 +480,7947 @@ pub mod nft_collaterized_loans {
 
             let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
+                token_interface::TransferChecked {
                     from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
                     to: ctx.accounts.lender_stablecoin_vault.to_account_info(),
                     authority: ctx.accounts.signer.to_account_info(), 
                 },
                 signer
             );
-            token::transfer(cpi_ctx, order.additional_collateral)?;
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
         }
-        nft_collaterized_loans.total_additional_collateral -= order.additional_collateral;
+        currency.total_additional_collateral -= order.additional_collateral;
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(nonce: u8)]
-pub struct Initialize<'info> {
-    #[account(
-        zero
-    )]
-    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+    pub fn add_sol_currency(ctx: Context<AddSolCurrency>, nonce: u8, vault_nonce: u8) -> Result<()> {
+        // Fund the escrow up to rent exemption so it can never be reaped.
+        {
+            let rent_reserve = Rent::get()?.minimum_balance(0);
+            let ix = system_instruction::transfer(
+                ctx.accounts.authority.key,
+                ctx.accounts.sol_vault.key,
+                rent_reserve.saturating_sub(ctx.accounts.sol_vault.lamports()),
+            );
+            invoke(
+                &ix,
+                &[
+                    ctx.accounts.authority.to_account_info(),
+                    ctx.accounts.sol_vault.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
 
-    pub stablecoin_mint: Box<Account<'info, Mint>>,
-    #[account(
-        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = stablecoin_vault.owner == signer.key(),
-    )]
-    pub stablecoin_vault: Box<Account<'info, TokenAccount>>,
+        let currency = &mut ctx.accounts.currency;
+        currency.market = ctx.accounts.nft_collaterized_loans.key();
+        currency.stablecoin_mint = native_mint::ID;
+        currency.stablecoin_vault = ctx.accounts.sol_vault.key();
+        currency.total_additional_collateral = 0;
+        currency.nonce = nonce;
+        currency.vault_nonce = vault_nonce;
 
-    #[account(
-        seeds = [
-            nft_collaterized_loans.to_account_info().key.as_ref()
-        ],
-        bump = nonce,
-    )]
-    pub signer: UncheckedAccount<'info>,
-}
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct CreateOrder<'info> {
-    #[account(
-        mut,
-        has_one = stablecoin_vault,
-        has_one = stablecoin_mint
-    )]
-    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+    pub fn create_sol_order(ctx: Context<CreateSolOrder>, nonce:u8, request_amount: u64, interest: u64, period: u64, additional_collateral: u64, expires_at: u64) -> Result<()> {
+        if request_amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
 
-    pub stablecoin_mint: Box<Account<'info, Mint>>,
-    #[account(
-        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = stablecoin_vault.owner == signer.key(),
-    )]
-    pub stablecoin_vault: Box<Account<'info, TokenAccount>>,
+        ctx.accounts.nft_collaterized_loans.check_loan_to_value(
+            ctx.accounts.instructions_sysvar.as_ref(),
+            &ctx.accounts.nft_mint.key(),
+            &native_mint::ID,
+            request_amount,
+            additional_collateral,
+        )?;
 
-    #[account(
-        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = user_stablecoin_vault.owner == borrower.key(),
-    )]
-    pub user_stablecoin_vault: Box<Account<'info, TokenAccount>>,
-    
-    #[account(
-        mut,
-        constraint = nft_mint.supply == 1,
-        constraint = nft_mint.decimals == 0,
-    )]
-    pub nft_mint: Box<Account<'info, Mint>>,
-    #[account(
-        constraint = nft_vault.mint == nft_mint.key(),
-        constraint = nft_vault.owner == signer.key(),
-    )]
-    pub nft_vault: Box<Account<'info, TokenAccount>>,
+        // Transfer collateral to vault.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.user_nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.nft_vault.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
 
-    #[account(
-        constraint = user_nft_vault.mint == nft_mint.key(),
-        constraint = user_nft_vault.owner == borrower.key(),
-    )]
-    pub user_nft_vault: Box<Account<'info, TokenAccount>>,
+        // Transfer additional collateral to escrow
+        {
+            let ix = system_instruction::transfer(
+                ctx.accounts.borrower.key,
+                ctx.accounts.sol_vault.key,
+                additional_collateral,
+            );
+            invoke(
+                &ix,
+                &[
+                    ctx.accounts.borrower.to_account_info(),
+                    ctx.accounts.sol_vault.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
 
-    // Order.
-    #[account(
-        init_if_needed,
-        payer = borrower,
-        seeds = [
-            nft_collaterized_loans.order_id.to_string().as_ref(),
-            nft_collaterized_loans.to_account_info().key().as_ref()
-        ],
-        bump
-    )]
-    pub order: Box<Account<'info, Order>>,
+        let clock = clock::Clock::get().unwrap();
 
-    #[account(mut)]
-    pub borrower: Signer<'info>,
+        // Save Info
+        let order = &mut ctx.accounts.order;
+        order.borrower = ctx.accounts.borrower.key();
+        order.stablecoin_vault = ctx.accounts.borrower.key();
+        order.stablecoin_mint = native_mint::ID;
+        order.nft_mint = ctx.accounts.nft_mint.key();
+        order.nft_vault = ctx.accounts.nft_vault.key();
+        order.request_amount = request_amount;
+        order.interest = interest;
+        order.period = period;
+        order.additional_collateral = additional_collateral;
+        order.lender = order.key(); // just a placeholder
+        order.created_at = clock.unix_timestamp as u64;
+        order.loan_start_time = 0; // placeholder
+        order.paid_back_at = 0;
+        order.withdrew_at = 0;
+        order.nonce = nonce;
+        order.expires_at = expires_at;
 
-    #[account(
-        seeds = [
-            nft_collaterized_loans.to_account_info().key.as_ref()
-        ],
-        bump = nft_collaterized_loans.nonce,
-    )]
-    pub signer: UncheckedAccount<'info>,
+        let currency = &mut ctx.accounts.currency;
+        currency.total_additional_collateral += additional_collateral;
 
-    // misc
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>
-}
+        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
+        nft_collaterized_loans.order_id += 1;
 
-#[derive(Accounts)]
-#[instruction(order_id: u64)]
-pub struct CancelOrder<'info> {
-    #[account(
-        mut,
-        has_one = stablecoin_vault,
-        has_one = stablecoin_mint
-    )]
-    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+        order.order_status = true;
 
-    // Order.
-    #[account(
-        mut,
-        constraint = order.stablecoin_vault == stablecoin_vault.key(),
-        constraint = order.borrower == borrower.key(),
-        constraint = order.nft_vault == nft_vault.key(),
-        constraint = order.nft_mint == nft_mint.key(),
-        seeds = [
-            order_id.to_string().as_ref(),
-            nft_collaterized_loans.to_account_info().key().as_ref()
-        ],
-        bump = order.nonce
-    )]
-    pub order: Box<Account<'info, Order>>,
+        Ok(())
+    }
 
-    pub stablecoin_mint: Box<Account<'info, Mint>>,
-    #[account(
-        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = stablecoin_vault.owner == signer.key(),
-    )]
-    pub stablecoin_vault: Box<Account<'info, TokenAccount>>,
+    pub fn cancel_sol_order(ctx: Context<CancelSolOrder>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
 
-    #[account(
-        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = user_stablecoin_vault.owner == borrower.key(),
-    )]
-    pub user_stablecoin_vault: Box<Account<'info, TokenAccount>>,
-    
-    #[account(
-        mut,
-        constraint = nft_mint.supply == 1,
-        constraint = nft_mint.decimals == 0,
-    )]
-    pub nft_mint: Box<Account<'info, Mint>>,
-    #[account(
-        constraint = nft_vault.mint == nft_mint.key(),
-        constraint = nft_vault.owner == signer.key(),
-    )]
-    pub nft_vault: Box<Account<'info, TokenAccount>>,
+        if order.loan_start_time != 0 && !order.order_status {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
 
-    #[account(
-        constraint = user_nft_vault.mint == nft_mint.key(),
-        constraint = user_nft_vault.owner == borrower.key(),
-    )]
-    pub user_nft_vault: Box<Account<'info, TokenAccount>>,
+        // Transfer back nft collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
 
-    #[account(mut)]
-    pub borrower: Signer<'info>,
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
 
-    #[account(
-        seeds = [
-            nft_collaterized_loans.to_account_info().key.as_ref()
-        ],
-        bump = nft_collaterized_loans.nonce,
-    )]
-    pub signer: UncheckedAccount<'info>,
+        // Transfer back additional collateral
+        {
+            let seeds = &[b"sol_vault".as_ref(), nft_collaterized_loans.to_account_info().key.as_ref(), &[currency.vault_nonce]];
+            let signer = &[&seeds[..]];
 
-    // misc
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>
-}
+            let ix = system_instruction::transfer(
+                ctx.accounts.sol_vault.key,
+                ctx.accounts.borrower.key,
+                order.additional_collateral,
+            );
+            invoke_signed(
+                &ix,
+                &[
+                    ctx.accounts.sol_vault.to_account_info(),
+                    ctx.accounts.borrower.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                signer
+            )?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
 
-#[derive(Accounts)]
-#[instruction(order_id: u64)]
-pub struct GiveLoan<'info> {
-    #[account(
+        order.order_status = false;
+
+        Ok(())
+    }
+
+    pub fn give_sol_loan(ctx: Context<GiveSolLoan>, _order_id: u64, request_amount: u64, interest: u64, period: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        if (order.loan_start_time != 0 && !order.order_status) || order.funded_amount != 0 {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        // Terms the lender agreed to must still hold.
+        if order.request_amount != request_amount || order.interest != interest || order.period != period {
+            return Err(ErrorCode::OrderTermsChanged.into());
+        }
+
+        if order.is_private {
+            match &ctx.accounts.allowlist {
+                Some(allowlist) if allowlist.lenders.contains(ctx.accounts.lender.key) => {}
+                _ => return Err(ErrorCode::LenderNotAllowed.into()),
+            }
+        }
+
+        if order.is_expired(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::OrderExpired.into());
+        }
+
+        ctx.accounts.nft_collaterized_loans.check_loan_to_value(
+            ctx.accounts.instructions_sysvar.as_ref(),
+            &order.nft_mint,
+            &order.stablecoin_mint,
+            order.request_amount,
+            order.additional_collateral,
+        )?;
+
+        // Transfer loan to the borrower
+        {
+            let ix = system_instruction::transfer(
+                ctx.accounts.lender.key,
+                ctx.accounts.borrower.key,
+                order.request_amount,
+            );
+            invoke(
+                &ix,
+                &[
+                    ctx.accounts.lender.to_account_info(),
+                    ctx.accounts.borrower.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        // Mint the promissory note to the lender
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.note_token_program.to_account_info(),
+                token_interface::MintTo {
+                    mint: ctx.accounts.note_mint.to_account_info(),
+                    to: ctx.accounts.lender_note_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::mint_to(cpi_ctx, 1)?;
+
+            ctx.accounts.note_metadata.create(
+                ctx.accounts.note_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.lender.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.note_token_program.to_account_info(),
+                signer,
+            )?;
+        }
+
+        // Save Info
+        order.lender = ctx.accounts.lender.key();
+        order.note_mint = ctx.accounts.note_mint.key();
+        order.loan_start_time = clock::Clock::get().unwrap().unix_timestamp as u64;
+        order.order_status = false;
+
+        Ok(())
+    }
+
+    pub fn payback_sol(ctx: Context<PaybackSol>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 && order.order_status {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        let clock = clock::Clock::get().unwrap();
+        if order.loan_start_time.checked_add(order.period).unwrap() < clock.unix_timestamp as u64 {
+            return Err(ErrorCode::RepaymentPeriodExceeded.into());
+        }
+
+        // Save Info
+        order.paid_back_at = clock.unix_timestamp as u64;
+
+        // Pay Loan
+        {
+            let ix = system_instruction::transfer(
+                ctx.accounts.payer.key,
+                ctx.accounts.lender.key,
+                order.request_amount.checked_add(order.interest).unwrap(),
+            );
+            invoke(
+                &ix,
+                &[
+                    ctx.accounts.payer.to_account_info(),
+                    ctx.accounts.lender.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        // Transfer back nft collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer back additional collateral
+        {
+            let seeds = &[b"sol_vault".as_ref(), nft_collaterized_loans.to_account_info().key.as_ref(), &[currency.vault_nonce]];
+            let signer = &[&seeds[..]];
+
+            let ix = system_instruction::transfer(
+                ctx.accounts.sol_vault.key,
+                ctx.accounts.borrower.key,
+                order.additional_collateral,
+            );
+            invoke_signed(
+                &ix,
+                &[
+                    ctx.accounts.sol_vault.to_account_info(),
+                    ctx.accounts.borrower.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                signer
+            )?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        Ok(())
+    }
+
+    pub fn liquidate_sol(ctx: Context<LiquidateSol>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 && order.order_status {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        let clock = clock::Clock::get().unwrap();
+        if !order.is_liquidatable(clock.unix_timestamp as u64) {
+            return Err(ErrorCode::RepaymentPeriodNotExceeded.into());
+        }
+
+        if order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Save Info
+        order.withdrew_at = clock.unix_timestamp as u64;
+
+        // Transfer nft collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer additional collateral
+        {
+            let seeds = &[b"sol_vault".as_ref(), nft_collaterized_loans.to_account_info().key.as_ref(), &[currency.vault_nonce]];
+            let signer = &[&seeds[..]];
+
+            let ix = system_instruction::transfer(
+                ctx.accounts.sol_vault.key,
+                ctx.accounts.lender.key,
+                order.additional_collateral,
+            );
+            invoke_signed(
+                &ix,
+                &[
+                    ctx.accounts.sol_vault.to_account_info(),
+                    ctx.accounts.lender.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                signer
+            )?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        Ok(())
+    }
+
+    pub fn create_escrowless_order(ctx: Context<CreateEscrowlessOrder>, nonce:u8, request_amount: u64, interest: u64, period: u64, additional_collateral: u64, expires_at: u64) -> Result<()> {
+        if request_amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        ctx.accounts.nft_collaterized_loans.check_loan_to_value(
+            ctx.accounts.instructions_sysvar.as_ref(),
+            &ctx.accounts.nft_mint.key(),
+            &ctx.accounts.stablecoin_mint.key(),
+            request_amount,
+            additional_collateral,
+        )?;
+
+        // Delegate the collateral to the market.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::Approve {
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    delegate: ctx.accounts.signer.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(),
+                },
+            );
+            token_interface::approve(cpi_ctx, 1)?;
+        }
+
+        // Freeze the collateral in the borrower's wallet.
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                metadata::FreezeDelegatedAccount {
+                    metadata: ctx.accounts.token_metadata_program.to_account_info(),
+                    delegate: ctx.accounts.signer.to_account_info(),
+                    token_account: ctx.accounts.user_nft_vault.to_account_info(),
+                    edition: ctx.accounts.nft_edition.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    token_program: ctx.accounts.nft_token_program.to_account_info(),
+                },
+                signer
+            );
+            metadata::freeze_delegated_account(cpi_ctx)?;
+        }
+
+        // Transfer additional collateral to vault
+        {
+            let balance_before = ctx.accounts.stablecoin_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), additional_collateral)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.stablecoin_vault.reload()?;
+            if ctx.accounts.stablecoin_vault.amount.checked_sub(balance_before).unwrap() != additional_collateral {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        let clock = clock::Clock::get().unwrap();
+
+        // Save Info
+        let order = &mut ctx.accounts.order;
+        order.borrower = ctx.accounts.borrower.key();
+        order.stablecoin_vault = ctx.accounts.user_stablecoin_vault.key();
+        order.stablecoin_mint = ctx.accounts.stablecoin_mint.key();
+        order.nft_mint = ctx.accounts.nft_mint.key();
+        order.nft_vault = ctx.accounts.user_nft_vault.key();
+        order.request_amount = request_amount;
+        order.interest = interest;
+        order.period = period;
+        order.additional_collateral = additional_collateral;
+        order.lender = order.key(); // just a placeholder
+        order.created_at = clock.unix_timestamp as u64;
+        order.loan_start_time = 0; // placeholder
+        order.paid_back_at = 0;
+        order.withdrew_at = 0;
+        order.nonce = nonce;
+        order.expires_at = expires_at;
+        order.is_escrowless = true;
+
+        let currency = &mut ctx.accounts.currency;
+        currency.total_additional_collateral += additional_collateral;
+
+        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
+        nft_collaterized_loans.order_id += 1;
+
+        order.order_status = true;
+
+        Ok(())
+    }
+
+    pub fn cancel_escrowless_order(ctx: Context<CancelEscrowlessOrder>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time != 0 && !order.order_status {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+        
+        // Thaw the collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                metadata::ThawDelegatedAccount {
+                    metadata: ctx.accounts.token_metadata_program.to_account_info(),
+                    delegate: ctx.accounts.signer.to_account_info(),
+                    token_account: ctx.accounts.user_nft_vault.to_account_info(),
+                    edition: ctx.accounts.nft_edition.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    token_program: ctx.accounts.nft_token_program.to_account_info(),
+                },
+                signer
+            );
+            metadata::thaw_delegated_account(cpi_ctx)?;
+        }
+
+        // Revoke the delegation.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::Revoke {
+                    source: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(),
+                },
+            );
+            token_interface::revoke(cpi_ctx)?;
+        }
+
+        // Transfer back additional collateral 
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        order.order_status = false;
+
+        // Sidenote: Preferred to close the account after this
+
+        Ok(())
+    }
+
+    pub fn payback_escrowless(ctx: Context<PaybackEscrowless>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 && order.order_status {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        Strategy::check_payout_vault(&ctx.accounts.note_holder, &ctx.accounts.lender_stablecoin_vault.key())?;
+
+        let clock = clock::Clock::get().unwrap();
+        if order.due_at() < clock.unix_timestamp as u64 {
+            return Err(ErrorCode::RepaymentPeriodExceeded.into());
+        }
+        
+        // Save Info
+        order.paid_back_at = clock.unix_timestamp as u64;
+
+        // Pay Loan
+        {
+            let repayment = order.request_amount.checked_add(order.interest_due(order.paid_back_at)).unwrap();
+            let balance_before = ctx.accounts.lender_stablecoin_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), repayment)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(), 
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.lender_stablecoin_vault.reload()?;
+            if ctx.accounts.lender_stablecoin_vault.amount.checked_sub(balance_before).unwrap() != repayment {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Thaw the collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                metadata::ThawDelegatedAccount {
+                    metadata: ctx.accounts.token_metadata_program.to_account_info(),
+                    delegate: ctx.accounts.signer.to_account_info(),
+                    token_account: ctx.accounts.user_nft_vault.to_account_info(),
+                    edition: ctx.accounts.nft_edition.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    token_program: ctx.accounts.nft_token_program.to_account_info(),
+                },
+                signer
+            );
+            metadata::thaw_delegated_account(cpi_ctx)?;
+        }
+
+        // Revoke the delegation.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::Revoke {
+                    source: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(),
+                },
+            );
+            token_interface::revoke(cpi_ctx)?;
+        }
+
+        // Transfer back additional collateral 
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        // Sidenote: Preferred to close the account after this
+
+        Ok(())
+    }
+
+    pub fn liquidate_escrowless(ctx: Context<LiquidateEscrowless>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 && order.order_status {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        let clock = clock::Clock::get().unwrap();
+        if !order.is_liquidatable(clock.unix_timestamp as u64) {
+            return Err(ErrorCode::RepaymentPeriodNotExceeded.into());
+        }
+        
+        if order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Save Info
+        order.withdrew_at = clock.unix_timestamp as u64;
+
+        // Thaw the collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                metadata::ThawDelegatedAccount {
+                    metadata: ctx.accounts.token_metadata_program.to_account_info(),
+                    delegate: ctx.accounts.signer.to_account_info(),
+                    token_account: ctx.accounts.nft_vault.to_account_info(),
+                    edition: ctx.accounts.nft_edition.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    token_program: ctx.accounts.nft_token_program.to_account_info(),
+                },
+                signer
+            );
+            metadata::thaw_delegated_account(cpi_ctx)?;
+        }
+
+        // Transfer nft collateral as the delegate.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer additional collateral 
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        Ok(())
+    }
+
+    pub fn set_margin_call_config(ctx: Context<SetMarginCallConfig>, margin_call_threshold_bps: u16, margin_call_period: u64) -> Result<()> {
+        if margin_call_threshold_bps as u64 > BPS_DENOMINATOR || (margin_call_threshold_bps != 0 && margin_call_period == 0) {
+            return Err(ErrorCode::InvalidMarginCallConfig.into());
+        }
+
+        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
+        nft_collaterized_loans.margin_call_threshold_bps = margin_call_threshold_bps;
+        nft_collaterized_loans.margin_call_period = margin_call_period;
+
+        Ok(())
+    }
+
+    pub fn margin_call(ctx: Context<MarginCall>, _order_id: u64) -> Result<()> {
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let order = &mut ctx.accounts.order;
+
+        if nft_collaterized_loans.appraiser == Pubkey::default() || nft_collaterized_loans.margin_call_threshold_bps == 0 {
+            return Err(ErrorCode::MarginCallsDisabled.into());
+        }
+
+        if order.loan_start_time == 0 || order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.margin_call_deadline != 0 {
+            return Err(ErrorCode::MarginCallActive.into());
+        }
+
+        let appraisal = Appraisal::load(
+            &ctx.accounts.instructions_sysvar.to_account_info(),
+            &nft_collaterized_loans.appraiser,
+            &order.nft_mint,
+            &order.stablecoin_mint,
+        )?;
+
+        let now = clock::Clock::get().unwrap().unix_timestamp;
+        if appraisal.appraised_at > now || (now - appraisal.appraised_at) as u64 > nft_collaterized_loans.max_appraisal_age {
+            return Err(ErrorCode::StaleAppraisal.into());
+        }
+
+        if order.health_bps(appraisal.price, now as u64) >= nft_collaterized_loans.margin_call_threshold_bps as u64 {
+            return Err(ErrorCode::LoanHealthy.into());
+        }
+
+        // Save Info
+        order.margin_call_price = appraisal.price;
+        order.margin_call_deadline = (now as u64).checked_add(nft_collaterized_loans.margin_call_period).unwrap();
+
+        Ok(())
+    }
+
+    pub fn top_up_collateral(ctx: Context<TopUpCollateral>, _order_id: u64, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        if ctx.accounts.order.paid_back_at != 0 || ctx.accounts.order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Transfer additional collateral to vault
+        {
+            let balance_before = ctx.accounts.stablecoin_vault.amount;
+            let gross_amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), amount)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, gross_amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.stablecoin_vault.reload()?;
+            if ctx.accounts.stablecoin_vault.amount.checked_sub(balance_before).unwrap() != amount {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        let currency = &mut ctx.accounts.currency;
+        currency.total_additional_collateral += amount;
+
+        let order = &mut ctx.accounts.order;
+        order.additional_collateral = order.additional_collateral.checked_add(amount).unwrap();
+        order.answer_margin_call(ctx.accounts.nft_collaterized_loans.margin_call_threshold_bps, clock::Clock::get().unwrap().unix_timestamp as u64);
+
+        Ok(())
+    }
+
+    pub fn top_up_sol_collateral(ctx: Context<TopUpSolCollateral>, _order_id: u64, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        if ctx.accounts.order.paid_back_at != 0 || ctx.accounts.order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Transfer additional collateral to escrow
+        {
+            let ix = system_instruction::transfer(
+                ctx.accounts.borrower.key,
+                ctx.accounts.sol_vault.key,
+                amount,
+            );
+            invoke(
+                &ix,
+                &[
+                    ctx.accounts.borrower.to_account_info(),
+                    ctx.accounts.sol_vault.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        let currency = &mut ctx.accounts.currency;
+        currency.total_additional_collateral += amount;
+
+        let order = &mut ctx.accounts.order;
+        order.additional_collateral = order.additional_collateral.checked_add(amount).unwrap();
+        order.answer_margin_call(ctx.accounts.nft_collaterized_loans.margin_call_threshold_bps, clock::Clock::get().unwrap().unix_timestamp as u64);
+
+        Ok(())
+    }
+
+    pub fn list_loan(ctx: Context<ListLoan>, _order_id: u64, nonce: u8, price: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+
+        if order.loan_start_time == 0 || order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        // Escrow the promissory note.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.note_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.seller_note_account.to_account_info(),
+                    mint: ctx.accounts.note_mint.to_account_info(),
+                    to: ctx.accounts.note_vault.to_account_info(),
+                    authority: ctx.accounts.seller.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.note_mint.decimals)?;
+        }
+
+        // Save Info
+        let listing = &mut ctx.accounts.listing;
+        listing.order = ctx.accounts.order.key();
+        listing.seller = ctx.accounts.seller.key();
+        listing.price = price;
+        listing.nonce = nonce;
+
+        Ok(())
+    }
+
+    pub fn buy_loan(ctx: Context<BuyLoan>, _order_id: u64, max_price: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+
+        if ctx.accounts.listing.price > max_price {
+            return Err(ErrorCode::PriceAboveMax.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Pay the seller
+        {
+            let price = ctx.accounts.listing.price;
+            let balance_before = ctx.accounts.seller_stablecoin_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), price)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.buyer_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.seller_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.buyer.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.seller_stablecoin_vault.reload()?;
+            if ctx.accounts.seller_stablecoin_vault.amount.checked_sub(balance_before).unwrap() != price {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Hand over the promissory note
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.note_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.note_vault.to_account_info(),
+                    mint: ctx.accounts.note_mint.to_account_info(),
+                    to: ctx.accounts.buyer_note_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.note_mint.decimals)?;
+        }
+
+        // Save Info
+        let order = &mut ctx.accounts.order;
+        order.lender = ctx.accounts.buyer.key();
+
+        Ok(())
+    }
+
+    pub fn delist_loan(ctx: Context<DelistLoan>, _order_id: u64) -> Result<()> {
+        // Return the promissory note
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.note_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.note_vault.to_account_info(),
+                    mint: ctx.accounts.note_mint.to_account_info(),
+                    to: ctx.accounts.seller_note_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.note_mint.decimals)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn transfer_borrower(ctx: Context<TransferBorrower>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        if order.loan_start_time == 0 {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Save Info
+        order.borrower = ctx.accounts.new_borrower.key();
+
+        Ok(())
+    }
+
+    pub fn surrender_collateral(ctx: Context<SurrenderCollateral>, _order_id: u64, forfeit_additional_collateral: bool) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 && order.order_status {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Save Info, the surrendered loan is settled as a default.
+        let clock = clock::Clock::get().unwrap();
+        order.withdrew_at = clock.unix_timestamp as u64;
+
+        // Transfer nft collateral.
+        if order.is_programmable {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            ctx.accounts.pnft.transfer(
+                ctx.accounts.nft_vault.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.user_nft_vault.to_account_info(),
+                ctx.accounts.lender.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.nft_token_program.to_account_info(),
+                signer,
+            )?;
+        } else {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer additional collateral, to the lender only when the borrower forfeits it.
+        {
+            let to = if forfeit_additional_collateral {
+                ctx.accounts.lender_stablecoin_vault.to_account_info()
+            } else {
+                ctx.accounts.user_stablecoin_vault.to_account_info()
+            };
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to,
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        emit!(CollateralSurrendered {
+            order: order.key(),
+            borrower: order.borrower,
+            lender: ctx.accounts.lender.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn surrender_sol_collateral(ctx: Context<SurrenderSolCollateral>, _order_id: u64, forfeit_additional_collateral: bool) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 && order.order_status {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Save Info, the surrendered loan is settled as a default.
+        let clock = clock::Clock::get().unwrap();
+        order.withdrew_at = clock.unix_timestamp as u64;
+
+        // Transfer nft collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer additional collateral, to the lender only when the borrower forfeits it.
+        {
+            let seeds = &[b"sol_vault".as_ref(), nft_collaterized_loans.to_account_info().key.as_ref(), &[currency.vault_nonce]];
+            let signer = &[&seeds[..]];
+
+            let to = if forfeit_additional_collateral {
+                ctx.accounts.lender.to_account_info()
+            } else {
+                ctx.accounts.borrower.to_account_info()
+            };
+            let ix = system_instruction::transfer(
+                ctx.accounts.sol_vault.key,
+                to.key,
+                order.additional_collateral,
+            );
+            invoke_signed(
+                &ix,
+                &[
+                    ctx.accounts.sol_vault.to_account_info(),
+                    to,
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                signer
+            )?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        emit!(CollateralSurrendered {
+            order: order.key(),
+            borrower: order.borrower,
+            lender: ctx.accounts.lender.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn surrender_escrowless_collateral(ctx: Context<SurrenderEscrowlessCollateral>, _order_id: u64, forfeit_additional_collateral: bool) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 && order.order_status {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Save Info, the surrendered loan is settled as a default.
+        let clock = clock::Clock::get().unwrap();
+        order.withdrew_at = clock.unix_timestamp as u64;
+
+        // Thaw the collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                metadata::ThawDelegatedAccount {
+                    metadata: ctx.accounts.token_metadata_program.to_account_info(),
+                    delegate: ctx.accounts.signer.to_account_info(),
+                    token_account: ctx.accounts.nft_vault.to_account_info(),
+                    edition: ctx.accounts.nft_edition.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    token_program: ctx.accounts.nft_token_program.to_account_info(),
+                },
+                signer
+            );
+            metadata::thaw_delegated_account(cpi_ctx)?;
+        }
+
+        // Transfer nft collateral as the delegate.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer additional collateral, to the lender only when the borrower forfeits it.
+        {
+            let to = if forfeit_additional_collateral {
+                ctx.accounts.lender_stablecoin_vault.to_account_info()
+            } else {
+                ctx.accounts.user_stablecoin_vault.to_account_info()
+            };
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to,
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        emit!(CollateralSurrendered {
+            order: order.key(),
+            borrower: order.borrower,
+            lender: ctx.accounts.lender.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn forgive_loan(ctx: Context<ForgiveLoan>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 && order.order_status {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Save Info, the forgiven loan is closed without repayment.
+        let clock = clock::Clock::get().unwrap();
+        order.paid_back_at = clock.unix_timestamp as u64;
+
+        // Transfer back nft collateral.
+        if order.is_programmable {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            ctx.accounts.pnft.transfer(
+                ctx.accounts.nft_vault.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.user_nft_vault.to_account_info(),
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.lender.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.nft_token_program.to_account_info(),
+                signer,
+            )?;
+        } else {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer back additional collateral 
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        Ok(())
+    }
+
+    pub fn forgive_sol_loan(ctx: Context<ForgiveSolLoan>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 && order.order_status {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Save Info, the forgiven loan is closed without repayment.
+        let clock = clock::Clock::get().unwrap();
+        order.paid_back_at = clock.unix_timestamp as u64;
+
+        // Transfer back nft collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer back additional collateral
+        {
+            let seeds = &[b"sol_vault".as_ref(), nft_collaterized_loans.to_account_info().key.as_ref(), &[currency.vault_nonce]];
+            let signer = &[&seeds[..]];
+
+            let ix = system_instruction::transfer(
+                ctx.accounts.sol_vault.key,
+                ctx.accounts.borrower.key,
+                order.additional_collateral,
+            );
+            invoke_signed(
+                &ix,
+                &[
+                    ctx.accounts.sol_vault.to_account_info(),
+                    ctx.accounts.borrower.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                signer
+            )?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        Ok(())
+    }
+
+    pub fn forgive_escrowless_loan(ctx: Context<ForgiveEscrowlessLoan>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 && order.order_status {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Save Info, the forgiven loan is closed without repayment.
+        let clock = clock::Clock::get().unwrap();
+        order.paid_back_at = clock.unix_timestamp as u64;
+
+        // Thaw the collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                metadata::ThawDelegatedAccount {
+                    metadata: ctx.accounts.token_metadata_program.to_account_info(),
+                    delegate: ctx.accounts.signer.to_account_info(),
+                    token_account: ctx.accounts.user_nft_vault.to_account_info(),
+                    edition: ctx.accounts.nft_edition.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    token_program: ctx.accounts.nft_token_program.to_account_info(),
+                },
+                signer
+            );
+            metadata::thaw_delegated_account(cpi_ctx)?;
+        }
+
+        // Revoke the delegation.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::Revoke {
+                    source: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(),
+                },
+            );
+            token_interface::revoke(cpi_ctx)?;
+        }
+
+        // Transfer back additional collateral 
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        Ok(())
+    }
+
+    pub fn extend_deadline(ctx: Context<ExtendDeadline>, _order_id: u64, period: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        if order.loan_start_time == 0 {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        if period <= order.period {
+            return Err(ErrorCode::PeriodNotExtended.into());
+        }
+
+        // Save Info
+        order.period = period;
+
+        Ok(())
+    }
+
+    pub fn expire_order(ctx: Context<ExpireOrder>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time != 0 || !order.order_status {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        if !order.is_expired(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::OrderNotExpired.into());
+        }
+
+        // Contributors withdraw before the order is closed.
+        if order.funded_amount != 0 {
+            return Err(ErrorCode::OrderPartiallyFunded.into());
+        }
+
+        // Transfer back nft collateral.
+        if order.is_programmable {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            ctx.accounts.pnft.transfer(
+                ctx.accounts.nft_vault.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.user_nft_vault.to_account_info(),
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.nft_token_program.to_account_info(),
+                signer,
+            )?;
+        } else {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer back additional collateral 
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        order.order_status = false;
+
+        Ok(())
+    }
+
+    pub fn expire_sol_order(ctx: Context<ExpireSolOrder>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time != 0 || !order.order_status {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        if !order.is_expired(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::OrderNotExpired.into());
+        }
+
+        // Transfer back nft collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer back additional collateral
+        {
+            let seeds = &[b"sol_vault".as_ref(), nft_collaterized_loans.to_account_info().key.as_ref(), &[currency.vault_nonce]];
+            let signer = &[&seeds[..]];
+
+            let ix = system_instruction::transfer(
+                ctx.accounts.sol_vault.key,
+                ctx.accounts.borrower.key,
+                order.additional_collateral,
+            );
+            invoke_signed(
+                &ix,
+                &[
+                    ctx.accounts.sol_vault.to_account_info(),
+                    ctx.accounts.borrower.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                signer
+            )?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        order.order_status = false;
+
+        Ok(())
+    }
+
+    pub fn expire_escrowless_order(ctx: Context<ExpireEscrowlessOrder>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time != 0 || !order.order_status {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        if !order.is_expired(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::OrderNotExpired.into());
+        }
+
+        // Thaw the collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                metadata::ThawDelegatedAccount {
+                    metadata: ctx.accounts.token_metadata_program.to_account_info(),
+                    delegate: ctx.accounts.signer.to_account_info(),
+                    token_account: ctx.accounts.user_nft_vault.to_account_info(),
+                    edition: ctx.accounts.nft_edition.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    token_program: ctx.accounts.nft_token_program.to_account_info(),
+                },
+                signer
+            );
+            metadata::thaw_delegated_account(cpi_ctx)?;
+        }
+
+        // Revoke the delegation.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::Revoke {
+                    source: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(),
+                },
+            );
+            token_interface::revoke(cpi_ctx)?;
+        }
+
+        // Transfer back additional collateral 
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(), 
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        order.order_status = false;
+
+        Ok(())
+    }
+
+    pub fn update_order(ctx: Context<UpdateOrder>, _order_id: u64, request_amount: u64, interest: u64, period: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        if order.loan_start_time != 0 || !order.order_status || order.funded_amount != 0 {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        // Open-term terms are set by make_perpetual.
+        if order.is_perpetual {
+            return Err(ErrorCode::OpenTermNotSupported.into());
+        }
+
+        if request_amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        ctx.accounts.nft_collaterized_loans.check_loan_to_value(
+            ctx.accounts.instructions_sysvar.as_ref(),
+            &order.nft_mint,
+            &order.stablecoin_mint,
+            request_amount,
+            order.additional_collateral,
+        )?;
+
+        // Save Info
+        order.request_amount = request_amount;
+        order.interest = interest;
+        order.period = period;
+
+        Ok(())
+    }
+
+    pub fn set_allowed_lenders(ctx: Context<SetAllowedLenders>, _order_id: u64, nonce: u8, lenders: Vec<Pubkey>) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        if order.loan_start_time != 0 || !order.order_status {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        if lenders.len() > Allowlist::MAX_LENDERS {
+            return Err(ErrorCode::TooManyLenders.into());
+        }
+
+        // Save Info, an empty list opens the order to everyone again.
+        order.is_private = !lenders.is_empty();
+
+        let allowlist = &mut ctx.accounts.allowlist;
+        allowlist.order = order.key();
+        allowlist.lenders = lenders;
+        allowlist.nonce = nonce;
+
+        Ok(())
+    }
+
+    pub fn make_counter_offer(ctx: Context<MakeCounterOffer>, _order_id: u64, nonce: u8, request_amount: u64, interest: u64, period: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+
+        if order.loan_start_time != 0 || !order.order_status {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        if request_amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        if order.is_private {
+            match &ctx.accounts.allowlist {
+                Some(allowlist) if allowlist.lenders.contains(ctx.accounts.lender.key) => {}
+                _ => return Err(ErrorCode::LenderNotAllowed.into()),
+            }
+        }
+
+        if order.is_expired(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::OrderExpired.into());
+        }
+
+        // Escrow enough for the borrower to receive the full amount.
+        let escrowed = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), request_amount)?;
+        {
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), escrowed)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.offer_vault.to_account_info(),
+                    authority: ctx.accounts.lender.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.offer_vault.reload()?;
+            if ctx.accounts.offer_vault.amount != escrowed {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Save Info
+        let counter_offer = &mut ctx.accounts.counter_offer;
+        counter_offer.order = ctx.accounts.order.key();
+        counter_offer.lender = ctx.accounts.lender.key();
+        counter_offer.request_amount = request_amount;
+        counter_offer.interest = interest;
+        counter_offer.period = period;
+        counter_offer.escrowed = escrowed;
+        counter_offer.nonce = nonce;
+
+        Ok(())
+    }
+
+    pub fn withdraw_counter_offer(ctx: Context<WithdrawCounterOffer>, _order_id: u64) -> Result<()> {
+        // Return the escrowed funds
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.offer_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, ctx.accounts.offer_vault.amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::CloseAccount {
+                    account: ctx.accounts.offer_vault.to_account_info(),
+                    destination: ctx.accounts.lender.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::close_account(cpi_ctx)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn accept_counter_offer(ctx: Context<AcceptCounterOffer>, _order_id: u64, request_amount: u64, interest: u64, period: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+        let counter_offer = &ctx.accounts.counter_offer;
+
+        if order.loan_start_time != 0 || !order.order_status || order.funded_amount != 0 {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        // The counter-offer must still carry the terms the borrower accepted.
+        if counter_offer.request_amount != request_amount || counter_offer.interest != interest || counter_offer.period != period {
+            return Err(ErrorCode::OrderTermsChanged.into());
+        }
+
+        if order.is_expired(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::OrderExpired.into());
+        }
+
+        ctx.accounts.nft_collaterized_loans.check_loan_to_value(
+            ctx.accounts.instructions_sysvar.as_ref(),
+            &order.nft_mint,
+            &order.stablecoin_mint,
+            counter_offer.request_amount,
+            order.additional_collateral,
+        )?;
+
+        // Release the escrowed loan to the borrower
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+            let balance_before = ctx.accounts.borrower_stablecoin_vault.amount;
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.offer_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.borrower_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, counter_offer.escrowed, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.borrower_stablecoin_vault.reload()?;
+            if ctx.accounts.borrower_stablecoin_vault.amount.checked_sub(balance_before).unwrap() != counter_offer.request_amount {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+
+            ctx.accounts.offer_vault.reload()?;
+            if ctx.accounts.offer_vault.amount != 0 {
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token_interface::TransferChecked {
+                        from: ctx.accounts.offer_vault.to_account_info(),
+                        mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                        to: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                        authority: ctx.accounts.signer.to_account_info(),
+                    },
+                    signer
+                );
+                token_interface::transfer_checked(cpi_ctx, ctx.accounts.offer_vault.amount, ctx.accounts.stablecoin_mint.decimals)?;
+            }
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::CloseAccount {
+                    account: ctx.accounts.offer_vault.to_account_info(),
+                    destination: ctx.accounts.lender.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::close_account(cpi_ctx)?;
+        }
+
+        // Mint the promissory note to the lender
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.note_token_program.to_account_info(),
+                token_interface::MintTo {
+                    mint: ctx.accounts.note_mint.to_account_info(),
+                    to: ctx.accounts.lender_note_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::mint_to(cpi_ctx, 1)?;
+
+            ctx.accounts.note_metadata.create(
+                ctx.accounts.note_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.note_token_program.to_account_info(),
+                signer,
+            )?;
+        }
+
+        // Save Info, the order takes the counter-offer's terms.
+        let order = &mut ctx.accounts.order;
+        let counter_offer = &ctx.accounts.counter_offer;
+        order.request_amount = counter_offer.request_amount;
+        order.interest = counter_offer.interest;
+        order.period = counter_offer.period;
+        order.lender = counter_offer.lender;
+        order.note_mint = ctx.accounts.note_mint.key();
+        order.loan_start_time = clock::Clock::get().unwrap().unix_timestamp as u64;
+        order.order_status = false;
+
+        Ok(())
+    }
+
+    pub fn fill_signed_order(ctx: Context<FillSignedOrder>, nonce: u8, bitmap_nonce: u8, nonce_page: u64) -> Result<()> {
+        let signed_order = SignedOrder::load(
+            &ctx.accounts.instructions_sysvar.to_account_info(),
+            &ctx.accounts.borrower.key(),
+            &ctx.accounts.nft_collaterized_loans.key(),
+            &ctx.accounts.nft_mint.key(),
+            &ctx.accounts.stablecoin_mint.key(),
+        )?;
+
+        if signed_order.request_amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        let clock = clock::Clock::get().unwrap();
+        if signed_order.expires_at != 0 && signed_order.expires_at <= clock.unix_timestamp as u64 {
+            return Err(ErrorCode::OrderExpired.into());
+        }
+
+        // Replay protection
+        if signed_order.nonce / OrderNonces::NONCES_PER_PAGE != nonce_page {
+            return Err(ErrorCode::InvalidOrderNonce.into());
+        }
+        let order_nonces = &mut ctx.accounts.order_nonces;
+        order_nonces.borrower = ctx.accounts.borrower.key();
+        order_nonces.page = nonce_page;
+        order_nonces.nonce = bitmap_nonce;
+        order_nonces.use_nonce(signed_order.nonce)?;
+
+        ctx.accounts.nft_collaterized_loans.check_loan_to_value(
+            Some(&ctx.accounts.instructions_sysvar),
+            &ctx.accounts.nft_mint.key(),
+            &ctx.accounts.stablecoin_mint.key(),
+            signed_order.request_amount,
+            0,
+        )?;
+
+        // Transfer collateral to vault as the pre-approved delegate.
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.user_nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        ctx.accounts.nft_vault.reload()?;
+        if ctx.accounts.nft_vault.amount != 1 {
+            return Err(ErrorCode::ReceivedAmountMismatch.into());
+        }
+
+        // Transfer loan to the borrower
+        {
+            let balance_before = ctx.accounts.borrower_stablecoin_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), signed_order.request_amount)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.borrower_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.lender.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.borrower_stablecoin_vault.reload()?;
+            if ctx.accounts.borrower_stablecoin_vault.amount.checked_sub(balance_before).unwrap() != signed_order.request_amount {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Mint the promissory note to the lender
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.note_token_program.to_account_info(),
+                token_interface::MintTo {
+                    mint: ctx.accounts.note_mint.to_account_info(),
+                    to: ctx.accounts.lender_note_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::mint_to(cpi_ctx, 1)?;
+
+            ctx.accounts.note_metadata.create(
+                ctx.accounts.note_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.lender.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.note_token_program.to_account_info(),
+                signer,
+            )?;
+        }
+
+        // Save Info
+        let order = &mut ctx.accounts.order;
+        order.borrower = ctx.accounts.borrower.key();
+        order.stablecoin_vault = ctx.accounts.borrower_stablecoin_vault.key();
+        order.stablecoin_mint = ctx.accounts.stablecoin_mint.key();
+        order.nft_mint = ctx.accounts.nft_mint.key();
+        order.nft_vault = ctx.accounts.nft_vault.key();
+        order.request_amount = signed_order.request_amount;
+        order.interest = signed_order.interest;
+        order.period = signed_order.period;
+        order.additional_collateral = 0;
+        order.lender = ctx.accounts.lender.key();
+        order.note_mint = ctx.accounts.note_mint.key();
+        order.created_at = clock.unix_timestamp as u64;
+        order.loan_start_time = clock.unix_timestamp as u64;
+        order.expires_at = signed_order.expires_at;
+        order.nonce = nonce;
+        order.order_status = false;
+
+        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
+        nft_collaterized_loans.order_id += 1;
+
+        Ok(())
+    }
+
+    pub fn contribute(ctx: Context<Contribute>, _order_id: u64, amount: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+
+        if order.loan_start_time != 0 || !order.order_status {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        if order.is_perpetual {
+            return Err(ErrorCode::OpenTermNotSupported.into());
+        }
+
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        if order.funded_amount.checked_add(amount).unwrap() > order.request_amount {
+            return Err(ErrorCode::ContributionTooLarge.into());
+        }
+
+        if order.is_private {
+            match &ctx.accounts.allowlist {
+                Some(allowlist) if allowlist.lenders.contains(ctx.accounts.lender.key) => {}
+                _ => return Err(ErrorCode::LenderNotAllowed.into()),
+            }
+        }
+
+        if order.is_expired(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::OrderExpired.into());
+        }
+
+        // Escrow enough for the borrower to receive the contribution.
+        let escrowed = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), amount)?;
+        {
+            let balance_before = ctx.accounts.syndicate_vault.amount;
+            let gross = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), escrowed)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.syndicate_vault.to_account_info(),
+                    authority: ctx.accounts.lender.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, gross, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.syndicate_vault.reload()?;
+            if ctx.accounts.syndicate_vault.amount.checked_sub(balance_before).unwrap() != escrowed {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Save Info
+        let share = &mut ctx.accounts.share;
+        share.order = ctx.accounts.order.key();
+        share.lender = ctx.accounts.lender.key();
+        share.amount = share.amount.checked_add(amount).unwrap();
+        share.escrowed = share.escrowed.checked_add(escrowed).unwrap();
+        share.nonce = ctx.bumps.share;
+
+        let order = &mut ctx.accounts.order;
+        order.is_syndicated = true;
+        order.funded_amount = order.funded_amount.checked_add(amount).unwrap();
+
+        if order.funded_amount < order.request_amount {
+            return Ok(());
+        }
+
+        // Fully funded, the loan starts.
+        ctx.accounts.nft_collaterized_loans.check_loan_to_value(
+            ctx.accounts.instructions_sysvar.as_ref(),
+            &order.nft_mint,
+            &order.stablecoin_mint,
+            order.request_amount,
+            order.additional_collateral,
+        )?;
+
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+            let balance_before = ctx.accounts.borrower_stablecoin_vault.amount;
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.syndicate_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.borrower_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, ctx.accounts.syndicate_vault.amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.borrower_stablecoin_vault.reload()?;
+            if ctx.accounts.borrower_stablecoin_vault.amount.checked_sub(balance_before).unwrap() < order.request_amount {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        order.lender = ctx.accounts.syndicate_vault.key();
+        order.loan_start_time = clock::Clock::get().unwrap().unix_timestamp as u64;
+        order.order_status = false;
+
+        Ok(())
+    }
+
+    pub fn withdraw_contribution(ctx: Context<WithdrawContribution>, _order_id: u64) -> Result<()> {
+        if ctx.accounts.order.loan_start_time != 0 {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        // Return the escrowed contribution
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.syndicate_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, ctx.accounts.share.escrowed, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+
+        // Save Info
+        let order = &mut ctx.accounts.order;
+        order.funded_amount = order.funded_amount.checked_sub(ctx.accounts.share.amount).unwrap();
+
+        Ok(())
+    }
+
+    pub fn payback_syndicated(ctx: Context<PaybackSyndicated>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.loan_start_time == 0 {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        let clock = clock::Clock::get().unwrap();
+        if order.loan_start_time.checked_add(order.period).unwrap() < clock.unix_timestamp as u64 {
+            return Err(ErrorCode::RepaymentPeriodExceeded.into());
+        }
+
+        // Save Info
+        let repayment = order.request_amount.checked_add(order.interest).unwrap();
+        order.paid_back_at = clock.unix_timestamp as u64;
+        order.syndicate_proceeds = repayment;
+
+        // Pay Loan into the syndicate vault
+        {
+            let balance_before = ctx.accounts.syndicate_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), repayment)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.payer_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.syndicate_vault.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.syndicate_vault.reload()?;
+            if ctx.accounts.syndicate_vault.amount.checked_sub(balance_before).unwrap() != repayment {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Transfer back nft collateral.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Transfer back additional collateral
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        Ok(())
+    }
+
+    pub fn bid_collateral(ctx: Context<BidCollateral>, _order_id: u64, amount: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+
+        if order.loan_start_time == 0 {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        let now = clock::Clock::get().unwrap().unix_timestamp as u64;
+        if !order.is_liquidatable(now) {
+            return Err(ErrorCode::RepaymentPeriodNotExceeded.into());
+        }
+
+        if order.auction_end != 0 && order.auction_end <= now {
+            return Err(ErrorCode::AuctionEnded.into());
+        }
+
+        if amount <= order.highest_bid {
+            return Err(ErrorCode::BidTooLow.into());
+        }
+
+        // The debt is the reserve, below it the lenders vote on who takes the collateral.
+        if amount < order.request_amount.checked_add(order.interest).unwrap() {
+            return Err(ErrorCode::BidBelowReserve.into());
+        }
+
+        // Escrow the bid
+        {
+            let balance_before = ctx.accounts.syndicate_vault.amount;
+            let gross = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), amount)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.bidder_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.syndicate_vault.to_account_info(),
+                    authority: ctx.accounts.bidder.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, gross, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.syndicate_vault.reload()?;
+            if ctx.accounts.syndicate_vault.amount.checked_sub(balance_before).unwrap() != amount {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Refund the outbid bidder
+        if order.highest_bid != 0 {
+            let previous_bidder_stablecoin_vault = ctx.accounts.previous_bidder_stablecoin_vault.as_ref().ok_or(ErrorCode::MissingBidderVault)?;
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.syndicate_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: previous_bidder_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.highest_bid, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+
+        // Save Info, the first bid opens the auction.
+        let order = &mut ctx.accounts.order;
+        if order.auction_end == 0 {
+            order.auction_end = now.checked_add(AUCTION_PERIOD).unwrap();
+        }
+        order.highest_bid = amount;
+        order.highest_bidder = ctx.accounts.bidder.key();
+
+        Ok(())
+    }
+
+    pub fn settle_auction(ctx: Context<SettleAuction>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        let clock = clock::Clock::get().unwrap();
+        if order.auction_end == 0 || order.auction_end > clock.unix_timestamp as u64 {
+            return Err(ErrorCode::AuctionNotEnded.into());
+        }
+
+        if order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // Save Info
+        order.withdrew_at = clock.unix_timestamp as u64;
+
+        // Transfer nft collateral to the winner.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.winner_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Additional collateral is shared by the lenders with the winning bid.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+            let balance_before = ctx.accounts.syndicate_vault.amount;
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.syndicate_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.syndicate_vault.reload()?;
+            let received = ctx.accounts.syndicate_vault.amount.checked_sub(balance_before).unwrap();
+            order.syndicate_proceeds = order.highest_bid.checked_add(received).unwrap();
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        Ok(())
+    }
+
+    pub fn claim_share(ctx: Context<ClaimShare>, _order_id: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+
+        if order.paid_back_at == 0 && order.withdrew_at == 0 {
+            return Err(ErrorCode::LoanNotSettled.into());
+        }
+
+        // Pro-rata part of the repayment or auction proceeds
+        let payout = (order.syndicate_proceeds as u128)
+            .checked_mul(ctx.accounts.share.amount as u128).unwrap()
+            .checked_div(order.request_amount as u128).unwrap() as u64;
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.syndicate_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, payout, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn create_pool(ctx: Context<CreatePool>, nonce: u8, collection: Pubkey, max_ltv_bps: u16, rate_model: RateModel, period: u64) -> Result<()> {
+        if max_ltv_bps as u64 > BPS_DENOMINATOR {
+            return Err(ErrorCode::InvalidLoanToValue.into());
+        }
+
+        rate_model.validate()?;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.market = ctx.accounts.nft_collaterized_loans.key();
+        pool.collection = collection;
+        pool.stablecoin_mint = ctx.accounts.stablecoin_mint.key();
+        pool.pool_vault = ctx.accounts.pool_vault.key();
+        pool.lp_mint = ctx.accounts.lp_mint.key();
+        pool.max_ltv_bps = max_ltv_bps;
+        pool.rate_model = rate_model;
+        pool.period = period;
+        pool.total_borrowed = 0;
+        pool.nonce = nonce;
+
+        Ok(())
+    }
+
+    pub fn deposit(ctx: Context<Deposit>, amount: u64, junior: bool) -> Result<()> {
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        let tranche_assets = ctx.accounts.pool.tranche_assets(junior);
+
+        // A tranche wiped out by losses still has holders, new deposits would be shared with them.
+        if tranche_assets == 0 && ctx.accounts.lp_mint.supply != 0 {
+            return Err(ErrorCode::TrancheWipedOut.into());
+        }
+
+        // Transfer liquidity to the pool, shares are priced on what arrives.
+        let balance_before = ctx.accounts.pool_vault.amount;
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.pool_vault.to_account_info(),
+                    authority: ctx.accounts.lender.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+        ctx.accounts.pool_vault.reload()?;
+        let received = ctx.accounts.pool_vault.amount.checked_sub(balance_before).unwrap();
+
+        let shares = LendingPool::shares_for(received, tranche_assets, ctx.accounts.lp_mint.supply);
+        if shares == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        // Save Info
+        let pool = &mut ctx.accounts.pool;
+        *pool.tranche_assets_mut(junior) = tranche_assets.checked_add(received).unwrap();
+
+        // Mint LP shares
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.lp_token_program.to_account_info(),
+                token_interface::MintTo {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    to: ctx.accounts.lender_lp_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::mint_to(cpi_ctx, shares)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn withdraw(ctx: Context<Withdraw>, shares: u64, junior: bool) -> Result<()> {
+        if shares == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        let tranche_assets = ctx.accounts.pool.tranche_assets(junior);
+        let amount = LendingPool::assets_for(shares, tranche_assets, ctx.accounts.lp_mint.supply);
+
+        // The junior tranche absorbs losses first, the part covering outstanding loans stays locked.
+        if junior && amount > tranche_assets.saturating_sub(ctx.accounts.pool.total_borrowed) {
+            return Err(ErrorCode::JuniorTrancheLocked.into());
+        }
+
+        // Only idle liquidity can leave the pool, and queued lenders leave first.
+        if amount > ctx.accounts.pool_vault.amount || ctx.accounts.pool.queued_shares != 0 {
+            return Err(ErrorCode::InsufficientLiquidity.into());
+        }
+
+        // Save Info
+        let pool = &mut ctx.accounts.pool;
+        *pool.tranche_assets_mut(junior) = tranche_assets.checked_sub(amount).unwrap();
+
+        // Burn LP shares
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.lp_token_program.to_account_info(),
+                token_interface::Burn {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    from: ctx.accounts.lender_lp_account.to_account_info(),
+                    authority: ctx.accounts.lender.to_account_info(),
+                },
+            );
+            token_interface::burn(cpi_ctx, shares)?;
+        }
+
+        // Transfer liquidity to the lender
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.pool_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn borrow_from_pool(ctx: Context<BorrowFromPool>, nonce: u8, request_amount: u64) -> Result<()> {
+        if request_amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        // Collateral must belong to the pool's collection.
+        let nft_metadata = &ctx.accounts.nft_metadata;
+        match &nft_metadata.collection {
+            Some(collection) if collection.verified && collection.key == ctx.accounts.pool.collection => {}
+            _ => return Err(ErrorCode::CollectionMismatch.into()),
+        }
+
+        let price = ctx.accounts.nft_collaterized_loans.appraise(
+            Some(&ctx.accounts.instructions_sysvar),
+            &ctx.accounts.nft_mint.key(),
+            &ctx.accounts.stablecoin_mint.key(),
+        )?;
+        let max_loan = (price as u128) * (ctx.accounts.pool.max_ltv_bps as u128) / (BPS_DENOMINATOR as u128);
+        if request_amount as u128 > max_loan {
+            return Err(ErrorCode::LoanToValueExceeded.into());
+        }
+
+        // Idle liquidity owed to the withdrawal queue can't be lent out.
+        let pool = &ctx.accounts.pool;
+        let reserved = LendingPool::assets_for(pool.queued_shares, pool.senior_assets, ctx.accounts.lp_mint.supply);
+        if request_amount > ctx.accounts.pool_vault.amount.saturating_sub(reserved) {
+            return Err(ErrorCode::InsufficientLiquidity.into());
+        }
+
+        // Transfer collateral to vault.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.user_nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.nft_vault.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        ctx.accounts.nft_vault.reload()?;
+        if ctx.accounts.nft_vault.amount != 1 {
+            return Err(ErrorCode::ReceivedAmountMismatch.into());
+        }
+
+        // Transfer loan to the borrower
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.pool_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.user_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, request_amount, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+
+        let clock = clock::Clock::get().unwrap();
+
+        // Save Info, the rate follows the utilization the loan brings the pool to.
+        let total_assets = ctx.accounts.pool.total_assets(ctx.accounts.pool_vault.amount);
+        let pool = &mut ctx.accounts.pool;
+        pool.total_borrowed = pool.total_borrowed.checked_add(request_amount).unwrap();
+        let interest_rate_bps = pool.rate_model.rate_bps(pool.total_borrowed, total_assets);
+
+        let order = &mut ctx.accounts.order;
+        order.borrower = ctx.accounts.borrower.key();
+        order.stablecoin_vault = ctx.accounts.user_stablecoin_vault.key();
+        order.stablecoin_mint = ctx.accounts.stablecoin_mint.key();
+        order.nft_mint = ctx.accounts.nft_mint.key();
+        order.nft_vault = ctx.accounts.nft_vault.key();
+        order.request_amount = request_amount;
+        order.interest_rate_bps = interest_rate_bps;
+        order.interest = RateModel::interest(request_amount, interest_rate_bps, pool.period);
+        order.period = pool.period;
+        order.additional_collateral = 0;
+        order.lender = pool.key();
+        order.pool = pool.key();
+        order.created_at = clock.unix_timestamp as u64;
+        order.loan_start_time = clock.unix_timestamp as u64;
+        order.nonce = nonce;
+        order.order_status = false;
+
+        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
+        nft_collaterized_loans.order_id += 1;
+
+        Ok(())
+    }
+
+    pub fn repay_pool_loan(ctx: Context<RepayPoolLoan>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        let clock = clock::Clock::get().unwrap();
+        if order.loan_start_time.checked_add(order.period).unwrap() < clock.unix_timestamp as u64 {
+            return Err(ErrorCode::RepaymentPeriodExceeded.into());
+        }
+
+        // Save Info
+        order.paid_back_at = clock.unix_timestamp as u64;
+        let pool = &mut ctx.accounts.pool;
+        pool.total_borrowed = pool.total_borrowed.checked_sub(order.request_amount).unwrap();
+        pool.settle(order.request_amount, order.request_amount.checked_add(order.interest).unwrap());
+
+        // Pay Loan into the pool
+        {
+            let repayment = order.request_amount.checked_add(order.interest).unwrap();
+            let balance_before = ctx.accounts.pool_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), repayment)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.payer_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.pool_vault.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.pool_vault.reload()?;
+            if ctx.accounts.pool_vault.amount.checked_sub(balance_before).unwrap() != repayment {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Transfer back nft collateral.
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_collateral_price(ctx: Context<SetCollateralPrice>, _order_id: u64, price: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        if !order.is_liquidatable(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::RepaymentPeriodNotExceeded.into());
+        }
+
+        // The authority is trusted to price defaulted collateral. Markets with an appraiser
+        // bound it from below by a fresh appraisal.
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        if price != 0 && nft_collaterized_loans.appraiser != Pubkey::default() {
+            let appraised = nft_collaterized_loans.appraise(ctx.accounts.instructions_sysvar.as_ref(), &order.nft_mint, &order.stablecoin_mint)?;
+            if price < appraised {
+                return Err(ErrorCode::PriceBelowAppraisal.into());
+            }
+        }
+
+        // Save Info
+        order.sale_price = price;
+
+        Ok(())
+    }
+
+    pub fn buy_pool_collateral(ctx: Context<BuyPoolCollateral>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        let clock = clock::Clock::get().unwrap();
+        if !order.is_liquidatable(clock.unix_timestamp as u64) {
+            return Err(ErrorCode::RepaymentPeriodNotExceeded.into());
+        }
+
+        // Defaulted collateral sells for the debt unless the pool set a price.
+        let price = if order.sale_price != 0 {
+            order.sale_price
+        } else {
+            order.request_amount.checked_add(order.interest).unwrap()
+        };
+
+        // Save Info, the loss or gain on the sale lands on the share price.
+        order.withdrew_at = clock.unix_timestamp as u64;
+        let pool = &mut ctx.accounts.pool;
+        pool.total_borrowed = pool.total_borrowed.checked_sub(order.request_amount).unwrap();
+        pool.settle(order.request_amount, price);
+
+        // Pay the pool
+        {
+            let balance_before = ctx.accounts.pool_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), price)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.buyer_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.pool_vault.to_account_info(),
+                    authority: ctx.accounts.buyer.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.pool_vault.reload()?;
+            if ctx.accounts.pool_vault.amount.checked_sub(balance_before).unwrap() != price {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Transfer nft collateral to the buyer.
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.buyer_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_rate_model(ctx: Context<SetRateModel>, rate_model: RateModel) -> Result<()> {
+        rate_model.validate()?;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.rate_model = rate_model;
+
+        Ok(())
+    }
+
+    pub fn queue_withdrawal(ctx: Context<QueueWithdrawal>, nonce: u8, shares: u64) -> Result<()> {
+        if shares < WithdrawalQueue::MIN_SHARES {
+            return Err(ErrorCode::WithdrawalTooSmall.into());
+        }
+
+        if ctx.accounts.withdrawal_queue.entries.len() >= WithdrawalQueue::MAX_ENTRIES {
+            return Err(ErrorCode::WithdrawalQueueFull.into());
+        }
+
+        // Lock the LP shares
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.lp_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.lender_lp_account.to_account_info(),
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    to: ctx.accounts.queue_lp_vault.to_account_info(),
+                    authority: ctx.accounts.lender.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, shares, ctx.accounts.lp_mint.decimals)?;
+        }
+
+        // Save Info
+        let withdrawal_queue = &mut ctx.accounts.withdrawal_queue;
+        withdrawal_queue.pool = ctx.accounts.pool.key();
+        withdrawal_queue.nonce = nonce;
+        withdrawal_queue.entries.push(QueuedWithdrawal {
+            lender: ctx.accounts.lender.key(),
+            payout_vault: ctx.accounts.lender_stablecoin_vault.key(),
+            shares,
+        });
+
+        let pool = &mut ctx.accounts.pool;
+        pool.queued_shares = pool.queued_shares.checked_add(shares).unwrap();
+
+        Ok(())
+    }
+
+    pub fn process_withdrawal_queue(ctx: Context<ProcessWithdrawalQueue>) -> Result<()> {
+        let entry = match ctx.accounts.withdrawal_queue.entries.first() {
+            Some(entry) => *entry,
+            None => return Err(ErrorCode::WithdrawalQueueEmpty.into()),
+        };
+
+        let senior_assets = ctx.accounts.pool.senior_assets;
+        let lp_supply = ctx.accounts.lp_mint.supply;
+
+        // A frozen payout account can't be paid, the entry moves to the back of the queue.
+        if ctx.accounts.payout_vault.is_frozen() {
+            ctx.accounts.withdrawal_queue.entries.rotate_left(1);
+            return Ok(());
+        }
+
+        // Shares that lost all their value are burned and dropped from the queue.
+        if LendingPool::assets_for(entry.shares, senior_assets, lp_supply) == 0 {
+            {
+                let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+                let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+                let signer = &[&seeds[..]];
+
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.lp_token_program.to_account_info(),
+                    token_interface::Burn {
+                        mint: ctx.accounts.lp_mint.to_account_info(),
+                        from: ctx.accounts.queue_lp_vault.to_account_info(),
+                        authority: ctx.accounts.signer.to_account_info(),
+                    },
+                    signer
+                );
+                token_interface::burn(cpi_ctx, entry.shares)?;
+            }
+
+            ctx.accounts.withdrawal_queue.entries.remove(0);
+            let pool = &mut ctx.accounts.pool;
+            pool.queued_shares = pool.queued_shares.checked_sub(entry.shares).unwrap();
+            return Ok(());
+        }
+
+        // Pay the head of the queue as far as idle liquidity allows.
+        let shares = entry.shares.min(LendingPool::shares_for(ctx.accounts.pool_vault.amount, senior_assets, lp_supply));
+        let amount = LendingPool::assets_for(shares, senior_assets, lp_supply);
+        if shares == 0 || amount == 0 {
+            return Err(ErrorCode::InsufficientLiquidity.into());
+        }
+
+        // Burn the locked LP shares
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.lp_token_program.to_account_info(),
+                token_interface::Burn {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    from: ctx.accounts.queue_lp_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::burn(cpi_ctx, shares)?;
+        }
+
+        // Transfer liquidity to the lender
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.pool_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.payout_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+        }
+
+        // Save Info
+        let withdrawal_queue = &mut ctx.accounts.withdrawal_queue;
+        if shares == entry.shares {
+            withdrawal_queue.entries.remove(0);
+        } else {
+            withdrawal_queue.entries[0].shares -= shares;
+        }
+
+        let pool = &mut ctx.accounts.pool;
+        pool.queued_shares = pool.queued_shares.checked_sub(shares).unwrap();
+        pool.senior_assets = pool.senior_assets.checked_sub(amount).unwrap();
+
+        Ok(())
+    }
+
+    pub fn enable_junior_tranche(ctx: Context<EnableJuniorTranche>, junior_interest_bps: u16) -> Result<()> {
+        if junior_interest_bps as u64 > BPS_DENOMINATOR {
+            return Err(ErrorCode::InvalidInterestShare.into());
+        }
+
+        let pool = &mut ctx.accounts.pool;
+        pool.junior_lp_mint = ctx.accounts.junior_lp_mint.key();
+        pool.junior_interest_bps = junior_interest_bps;
+
+        Ok(())
+    }
+
+    pub fn create_strategy(ctx: Context<CreateStrategy>, nonce: u8, collections: Vec<Pubkey>, max_amount: u64, min_interest_bps: u64, max_period: u64) -> Result<()> {
+        if collections.len() > Strategy::MAX_COLLECTIONS {
+            return Err(ErrorCode::TooManyCollections.into());
+        }
+
+        let strategy = &mut ctx.accounts.strategy;
+        strategy.market = ctx.accounts.nft_collaterized_loans.key();
+        strategy.owner = ctx.accounts.owner.key();
+        strategy.stablecoin_mint = ctx.accounts.stablecoin_mint.key();
+        strategy.strategy_vault = ctx.accounts.strategy_vault.key();
+        strategy.collections = collections;
+        strategy.max_amount = max_amount;
+        strategy.min_interest_bps = min_interest_bps;
+        strategy.max_period = max_period;
+        strategy.nonce = nonce;
+
+        Ok(())
+    }
+
+    pub fn fund_strategy(ctx: Context<FundStrategy>, amount: u64) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token_interface::TransferChecked {
+                from: ctx.accounts.owner_stablecoin_vault.to_account_info(),
+                mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                to: ctx.accounts.strategy_vault.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        );
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+        Ok(())
+    }
+
+    pub fn withdraw_from_strategy(ctx: Context<WithdrawFromStrategy>, amount: u64) -> Result<()> {
+        let strategy = &ctx.accounts.strategy;
+        let seeds = &[
+            b"strategy".as_ref(),
+            strategy.market.as_ref(),
+            strategy.owner.as_ref(),
+            strategy.stablecoin_mint.as_ref(),
+            &[strategy.nonce],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token_interface::TransferChecked {
+                from: ctx.accounts.strategy_vault.to_account_info(),
+                mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                to: ctx.accounts.owner_stablecoin_vault.to_account_info(),
+                authority: ctx.accounts.strategy.to_account_info(),
+            },
+            signer
+        );
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+        Ok(())
+    }
+
+    pub fn withdraw_strategy_note(ctx: Context<WithdrawStrategyNote>) -> Result<()> {
+        let strategy = &ctx.accounts.strategy;
+        let seeds = &[
+            b"strategy".as_ref(),
+            strategy.market.as_ref(),
+            strategy.owner.as_ref(),
+            strategy.stablecoin_mint.as_ref(),
+            &[strategy.nonce],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.note_token_program.to_account_info(),
+            token_interface::TransferChecked {
+                from: ctx.accounts.strategy_note_account.to_account_info(),
+                mint: ctx.accounts.note_mint.to_account_info(),
+                to: ctx.accounts.owner_note_account.to_account_info(),
+                authority: ctx.accounts.strategy.to_account_info(),
+            },
+            signer
+        );
+        token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.note_mint.decimals)?;
+
+        Ok(())
+    }
+
+    pub fn relend(ctx: Context<Relend>, _order_id: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+        let strategy = &ctx.accounts.strategy;
+
+        if order.loan_start_time != 0 || !order.order_status || order.funded_amount != 0 {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        if order.is_private || order.is_perpetual || order.is_expired(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::StrategyMismatch.into());
+        }
+
+        // The order must match the lender's criteria.
+        let interest_bps = (order.interest as u128) * (BPS_DENOMINATOR as u128) / (order.request_amount as u128);
+        if order.request_amount > strategy.max_amount || interest_bps < strategy.min_interest_bps as u128 || order.period > strategy.max_period {
+            return Err(ErrorCode::StrategyMismatch.into());
+        }
+        if !strategy.collections.is_empty() {
+            match &ctx.accounts.nft_metadata.collection {
+                Some(collection) if collection.verified && strategy.collections.contains(&collection.key) => {}
+                _ => return Err(ErrorCode::StrategyMismatch.into()),
+            }
+        }
+
+        ctx.accounts.nft_collaterized_loans.check_loan_to_value(
+            ctx.accounts.instructions_sysvar.as_ref(),
+            &order.nft_mint,
+            &order.stablecoin_mint,
+            order.request_amount,
+            order.additional_collateral,
+        )?;
+
+        // Transfer loan to the borrower
+        {
+            let strategy = &ctx.accounts.strategy;
+            let seeds = &[
+                b"strategy".as_ref(),
+                strategy.market.as_ref(),
+                strategy.owner.as_ref(),
+                strategy.stablecoin_mint.as_ref(),
+                &[strategy.nonce],
+            ];
+            let signer = &[&seeds[..]];
+            let balance_before = ctx.accounts.borrower_stablecoin_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), order.request_amount)?;
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.strategy_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.borrower_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.strategy.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.borrower_stablecoin_vault.reload()?;
+            if ctx.accounts.borrower_stablecoin_vault.amount.checked_sub(balance_before).unwrap() != order.request_amount {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Mint the promissory note to the strategy, repayments land in its vault.
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.note_token_program.to_account_info(),
+                token_interface::MintTo {
+                    mint: ctx.accounts.note_mint.to_account_info(),
+                    to: ctx.accounts.strategy_note_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::mint_to(cpi_ctx, 1)?;
+
+            ctx.accounts.note_metadata.create(
+                ctx.accounts.note_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.note_token_program.to_account_info(),
+                signer,
+            )?;
+        }
+
+        // Save Info
+        let order = &mut ctx.accounts.order;
+        order.lender = ctx.accounts.strategy.key();
+        order.note_mint = ctx.accounts.note_mint.key();
+        order.loan_start_time = clock::Clock::get().unwrap().unix_timestamp as u64;
+        order.order_status = false;
+
+        Ok(())
+    }
+
+    pub fn make_perpetual(ctx: Context<MakePerpetual>, _order_id: u64, interest_rate_bps: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        if order.loan_start_time != 0 || !order.order_status || order.funded_amount != 0 {
+            return Err(ErrorCode::LoanAlreadyStarted.into());
+        }
+
+        // Only token loans outside syndicates and pools can be open-term.
+        if order.is_perpetual || order.is_syndicated || order.pool != Pubkey::default() || order.stablecoin_mint == native_mint::ID {
+            return Err(ErrorCode::OpenTermNotSupported.into());
+        }
+
+        // Save Info
+        order.is_perpetual = true;
+        order.interest = 0;
+        order.interest_rate_bps = interest_rate_bps;
+
+        Ok(())
+    }
+
+    pub fn call_loan(ctx: Context<CallLoan>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        if order.loan_start_time == 0 {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        if !order.is_perpetual {
+            return Err(ErrorCode::NotOpenTerm.into());
+        }
+
+        if order.called_at != 0 {
+            return Err(ErrorCode::LoanAlreadyCalled.into());
+        }
+
+        // Save Info
+        order.called_at = clock::Clock::get().unwrap().unix_timestamp as u64;
+
+        Ok(())
+    }
+
+    pub fn refinance(ctx: Context<Refinance>, _order_id: u64) -> Result<()> {
+        let order = &ctx.accounts.order;
+
+        if !order.is_perpetual {
+            return Err(ErrorCode::NotOpenTerm.into());
+        }
+
+        Strategy::check_payout_vault(&ctx.accounts.note_holder, &ctx.accounts.old_lender_stablecoin_vault.key())?;
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        if order.called_at == 0 {
+            return Err(ErrorCode::LoanNotCalled.into());
+        }
+
+        let now = clock::Clock::get().unwrap().unix_timestamp as u64;
+        if order.due_at() < now {
+            return Err(ErrorCode::RepaymentPeriodExceeded.into());
+        }
+
+        // Pay off the current lender, the new one lends at the current auction rate.
+        let payoff = order.request_amount.checked_add(order.interest_due(now)).unwrap();
+        let interest_rate_bps = order.refinance_rate_bps(now, ctx.accounts.nft_collaterized_loans.max_refinance_rate_bps);
+        {
+            let balance_before = ctx.accounts.old_lender_stablecoin_vault.amount;
+            let amount = transfer_amount_with_fee(&ctx.accounts.stablecoin_mint.to_account_info(), payoff)?;
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.lender_stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.old_lender_stablecoin_vault.to_account_info(),
+                    authority: ctx.accounts.lender.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.old_lender_stablecoin_vault.reload()?;
+            if ctx.accounts.old_lender_stablecoin_vault.amount.checked_sub(balance_before).unwrap() != payoff {
+                return Err(ErrorCode::ReceivedAmountMismatch.into());
+            }
+        }
+
+        // Mint a new promissory note to the lender, the old one no longer matches the order.
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.note_token_program.to_account_info(),
+                token_interface::MintTo {
+                    mint: ctx.accounts.note_mint.to_account_info(),
+                    to: ctx.accounts.lender_note_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::mint_to(cpi_ctx, 1)?;
+
+            ctx.accounts.note_metadata.create(
+                ctx.accounts.note_mint.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.lender.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.note_token_program.to_account_info(),
+                signer,
+            )?;
+        }
+
+        // Save Info, the accrued interest rolls into the principal.
+        let order = &mut ctx.accounts.order;
+        order.request_amount = payoff;
+        order.interest_rate_bps = interest_rate_bps;
+        order.lender = ctx.accounts.lender.key();
+        order.note_mint = ctx.accounts.note_mint.key();
+        order.loan_start_time = now;
+        order.called_at = 0;
+
+        Ok(())
+    }
+
+    pub fn set_refinance_config(ctx: Context<SetRefinanceConfig>, max_refinance_rate_bps: u64) -> Result<()> {
+        let nft_collaterized_loans = &mut ctx.accounts.nft_collaterized_loans;
+        nft_collaterized_loans.max_refinance_rate_bps = max_refinance_rate_bps;
+
+        Ok(())
+    }
+
+    pub fn vote_claim(ctx: Context<VoteClaim>, _order_id: u64, claimant: Pubkey) -> Result<()> {
+        let order = &ctx.accounts.order;
+
+        if order.loan_start_time == 0 {
+            return Err(ErrorCode::LoanNotProvided.into());
+        }
+
+        if order.paid_back_at != 0 || order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        if !order.is_liquidatable(clock::Clock::get().unwrap().unix_timestamp as u64) {
+            return Err(ErrorCode::RepaymentPeriodNotExceeded.into());
+        }
+
+        if ctx.accounts.share.voted {
+            return Err(ErrorCode::AlreadyVoted.into());
+        }
+
+        // Save Info, votes are weighted by the principal of the share.
+        let claim_vote = &mut ctx.accounts.claim_vote;
+        if claim_vote.order == Pubkey::default() {
+            claim_vote.order = order.key();
+            claim_vote.claimant = claimant;
+            claim_vote.nonce = ctx.bumps.claim_vote;
+        }
+        claim_vote.votes = claim_vote.votes.checked_add(ctx.accounts.share.amount).unwrap();
+        ctx.accounts.share.voted = true;
+
+        Ok(())
+    }
+
+    pub fn settle_claim(ctx: Context<SettleClaim>, _order_id: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+        let currency = &mut ctx.accounts.currency;
+
+        if order.withdrew_at != 0 {
+            return Err(ErrorCode::AlreadyLiquidated.into());
+        }
+
+        // A bid at or above the reserve goes to auction instead.
+        if order.highest_bid != 0 {
+            return Err(ErrorCode::AuctionStarted.into());
+        }
+
+        // Lenders holding a majority of the principal must back the claimant.
+        if (ctx.accounts.claim_vote.votes as u128) * 2 <= order.request_amount as u128 {
+            return Err(ErrorCode::ClaimNotApproved.into());
+        }
+
+        // Save Info
+        let clock = clock::Clock::get().unwrap();
+        order.withdrew_at = clock.unix_timestamp as u64;
+
+        // Transfer nft collateral to the claimant.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.nft_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.claimant_nft_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
+        }
+
+        // Additional collateral is shared by the lenders.
+        {
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+            let balance_before = ctx.accounts.syndicate_vault.amount;
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.stablecoin_vault.to_account_info(),
+                    mint: ctx.accounts.stablecoin_mint.to_account_info(),
+                    to: ctx.accounts.syndicate_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, order.additional_collateral, ctx.accounts.stablecoin_mint.decimals)?;
+
+            ctx.accounts.syndicate_vault.reload()?;
+            order.syndicate_proceeds = ctx.accounts.syndicate_vault.amount.checked_sub(balance_before).unwrap();
+        }
+        currency.total_additional_collateral -= order.additional_collateral;
+
+        Ok(())
+    }
+
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
+        let lender = ctx.accounts.lender.key();
+        let shares: u64 = ctx.accounts.withdrawal_queue.entries.iter()
+            .filter(|entry| entry.lender == lender)
+            .map(|entry| entry.shares)
+            .sum();
+        if shares == 0 {
+            return Err(ErrorCode::WithdrawalQueueEmpty.into());
+        }
+
+        // Return the locked LP shares
+        {
+            let nft_collaterized_loans = &ctx.accounts.nft_collaterized_loans;
+            let seeds = &[nft_collaterized_loans.to_account_info().key.as_ref(), &[nft_collaterized_loans.nonce]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.lp_token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.queue_lp_vault.to_account_info(),
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    to: ctx.accounts.lender_lp_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                signer
+            );
+            token_interface::transfer_checked(cpi_ctx, shares, ctx.accounts.lp_mint.decimals)?;
+        }
+
+        // Save Info
+        ctx.accounts.withdrawal_queue.entries.retain(|entry| entry.lender != lender);
+
+        let pool = &mut ctx.accounts.pool;
+        pool.queued_shares = pool.queued_shares.checked_sub(shares).unwrap();
+
+        Ok(())
+    }
+}
+
+// Amount to send so that `amount` arrives after the mint's transfer fee, if it has one.
+pub fn transfer_amount_with_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    if *mint.owner != token_2022::ID {
+        return Ok(amount);
+    }
+
+    let data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<token_2022::spl_token_2022::state::Mint>::unpack(&data)?;
+    match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(config) => {
+            let fee = config
+                .get_epoch_fee(Clock::get()?.epoch)
+                .calculate_inverse_fee(amount)
+                .ok_or(ErrorCode::ReceivedAmountMismatch)?;
+            Ok(amount.checked_add(fee).unwrap())
+        }
+        Err(_) => Ok(amount),
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct Initialize<'info> {
+    #[account(
+        zero
+    )]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAppraisalConfig<'info> {
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct AddCurrency<'info> {
+    #[account(
+        has_one = authority
+    )]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Allowlist entry for the mint.
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Currency::INIT_SPACE,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateOrder<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Order.
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        space = 8 + Order::INIT_SPACE,
+        seeds = [
+            nft_collaterized_loans.order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Programmable nft accounts, only needed when the collateral is a pNFT.
+    pub pnft: ProgrammableNft<'info>,
+
+    /// CHECK: instructions sysvar, only needed when the market requires an appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.borrower == borrower.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Programmable nft accounts, only needed when the collateral is a pNFT.
+    pub pnft: ProgrammableNft<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct GiveLoan<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.borrower != lender.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = borrower_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = borrower_stablecoin_vault.owner == order.borrower,
+        constraint = borrower_stablecoin_vault.key() == order.stablecoin_vault,
+    )]
+    pub borrower_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    // Promissory note minted to the lender.
+    #[account(
+        init,
+        payer = lender,
+        seeds = [
+            b"note".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+        mint::decimals = 0,
+        mint::authority = signer,
+        mint::freeze_authority = signer,
+        mint::token_program = note_token_program,
+    )]
+    pub note_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = lender,
+        associated_token::mint = note_mint,
+        associated_token::authority = lender,
+        associated_token::token_program = note_token_program,
+    )]
+    pub lender_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    // Token Metadata accounts of the promissory note.
+    pub note_metadata: NoteMetadata<'info>,
+
+    // Lenders the borrower restricted the order to, only needed for private orders.
+    #[account(
+        seeds = [
+            b"allowlist".as_ref(),
+            order.key().as_ref()
+        ],
+        bump = allowlist.nonce
+    )]
+    pub allowlist: Option<Box<Account<'info, Allowlist>>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    /// CHECK: instructions sysvar, only needed when the market requires an appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub note_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct Payback<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.borrower == borrower.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == note_account.owner,
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = payer_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = payer_stablecoin_vault.owner == payer.key(),
+    )]
+    pub payer_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Collateral is returned to the borrower, who need not sign.
+    pub borrower: SystemAccount<'info>,
+
+    // Repays the loan, may be anyone.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Programmable nft accounts, only needed when the collateral is a pNFT.
+    pub pnft: ProgrammableNft<'info>,
+
+    // Promissory note of the loan, its holder is paid.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner != signer.key() @ ErrorCode::LoanListed,
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: holder of the note, checked for a strategy in the handler
+    #[account(
+        address = note_account.owner,
+    )]
+    pub note_holder: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct Liquidate<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == lender.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Programmable nft accounts, only needed when the collateral is a pNFT.
+    pub pnft: ProgrammableNft<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8, vault_nonce: u8)]
+pub struct AddSolCurrency<'info> {
+    #[account(
+        has_one = authority
+    )]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Allowlist entry for native SOL.
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Currency::INIT_SPACE,
+        seeds = [
+            b"currency".as_ref(),
+            native_mint::ID.as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Lamport escrow holding the additional collateral of SOL orders.
+    #[account(
+        mut,
+        seeds = [
+            b"sol_vault".as_ref(),
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = vault_nonce,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateSolOrder<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        constraint = currency.stablecoin_vault == sol_vault.key(),
+        seeds = [
+            b"currency".as_ref(),
+            native_mint::ID.as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    #[account(mut)]
+    pub sol_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+        // Programmable nfts stay frozen in the wallet and are only supported by token orders.
+        constraint = !user_nft_vault.is_frozen() @ ErrorCode::ProgrammableNotSupported,
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Order.
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        space = 8 + Order::INIT_SPACE,
+        seeds = [
+            nft_collaterized_loans.order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    /// CHECK: instructions sysvar, only needed when the market requires an appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct CancelSolOrder<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        constraint = currency.stablecoin_vault == sol_vault.key(),
+        seeds = [
+            b"currency".as_ref(),
+            native_mint::ID.as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    #[account(mut)]
+    pub sol_vault: SystemAccount<'info>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == native_mint::ID,
+        constraint = order.borrower == borrower.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct GiveSolLoan<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == native_mint::ID,
+        constraint = order.borrower != lender.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        constraint = borrower.key() == order.stablecoin_vault,
+    )]
+    pub borrower: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    // Promissory note minted to the lender.
+    #[account(
+        init,
+        payer = lender,
+        seeds = [
+            b"note".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+        mint::decimals = 0,
+        mint::authority = signer,
+        mint::freeze_authority = signer,
+        mint::token_program = note_token_program,
+    )]
+    pub note_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = lender,
+        associated_token::mint = note_mint,
+        associated_token::authority = lender,
+        associated_token::token_program = note_token_program,
+    )]
+    pub lender_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    // Token Metadata accounts of the promissory note.
+    pub note_metadata: NoteMetadata<'info>,
+
+    // Lenders the borrower restricted the order to, only needed for private orders.
+    #[account(
+        seeds = [
+            b"allowlist".as_ref(),
+            order.key().as_ref()
+        ],
+        bump = allowlist.nonce
+    )]
+    pub allowlist: Option<Box<Account<'info, Allowlist>>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    /// CHECK: instructions sysvar, only needed when the market requires an appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub note_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PaybackSol<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        constraint = currency.stablecoin_vault == sol_vault.key(),
+        seeds = [
+            b"currency".as_ref(),
+            native_mint::ID.as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    #[account(mut)]
+    pub sol_vault: SystemAccount<'info>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == native_mint::ID,
+        constraint = order.borrower == borrower.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        constraint = lender.key() == note_account.owner,
+    )]
+    pub lender: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Collateral is returned to the borrower, who need not sign.
+    #[account(mut)]
+    pub borrower: SystemAccount<'info>,
+
+    // Repays the loan, may be anyone.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Promissory note of the loan, its holder is paid.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner != signer.key() @ ErrorCode::LoanListed,
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct LiquidateSol<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        constraint = currency.stablecoin_vault == sol_vault.key(),
+        seeds = [
+            b"currency".as_ref(),
+            native_mint::ID.as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    #[account(mut)]
+    pub sol_vault: SystemAccount<'info>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == native_mint::ID,
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == lender.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+pub struct CreateEscrowlessOrder<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: master edition of the nft, validated by Token Metadata against the mint
+    pub nft_edition: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Order.
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        space = 8 + Order::INIT_SPACE,
+        seeds = [
+            nft_collaterized_loans.order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    /// CHECK: instructions sysvar, only needed when the market requires an appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Program<'info, Token>,
+    pub token_metadata_program: Program<'info, Metadata>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct CancelEscrowlessOrder<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.borrower == borrower.key(),
+        constraint = order.is_escrowless,
+        constraint = order.nft_vault == user_nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: master edition of the nft, validated by Token Metadata against the mint
+    pub nft_edition: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Program<'info, Token>,
+    pub token_metadata_program: Program<'info, Metadata>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PaybackEscrowless<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.borrower == borrower.key(),
+        constraint = order.is_escrowless,
+        constraint = order.nft_vault == user_nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == note_account.owner,
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: master edition of the nft, validated by Token Metadata against the mint
+    pub nft_edition: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Promissory note of the loan, its holder is paid.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner != signer.key() @ ErrorCode::LoanListed,
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: holder of the note, checked for a strategy in the handler
+    #[account(
+        address = note_account.owner,
+    )]
+    pub note_holder: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Program<'info, Token>,
+    pub token_metadata_program: Program<'info, Metadata>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct LiquidateEscrowless<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.is_escrowless,
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == order.borrower,
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: master edition of the nft, validated by Token Metadata against the mint
+    pub nft_edition: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == lender.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Program<'info, Token>,
+    pub token_metadata_program: Program<'info, Metadata>
+}
+
+#[derive(Accounts)]
+pub struct SetMarginCallConfig<'info> {
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct MarginCall<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub lender: Signer<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: instructions sysvar carrying the signed appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct TopUpCollateral<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = borrower,
+        has_one = stablecoin_mint,
+        // pool loans carry no additional collateral
+        constraint = order.pool == Pubkey::default(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub borrower: Signer<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct TopUpSolCollateral<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        constraint = currency.stablecoin_vault == sol_vault.key(),
+        seeds = [
+            b"currency".as_ref(),
+            native_mint::ID.as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    #[account(mut)]
+    pub sol_vault: SystemAccount<'info>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = borrower,
+        constraint = order.stablecoin_mint == native_mint::ID,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, nonce: u8)]
+pub struct ListLoan<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + Listing::INIT_SPACE,
+        seeds = [
+            b"listing".as_ref(),
+            order.key().as_ref()
+        ],
+        bump
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        address = order.note_mint,
+    )]
+    pub note_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = note_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = note_token_program,
+    )]
+    pub note_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = seller_note_account.mint == note_mint.key(),
+        constraint = seller_note_account.owner == seller.key(),
+    )]
+    pub seller_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub note_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct BuyLoan<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        close = seller,
+        has_one = seller,
+        has_one = order,
+        seeds = [
+            b"listing".as_ref(),
+            order.key().as_ref()
+        ],
+        bump = listing.nonce
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        address = order.note_mint,
+    )]
+    pub note_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = note_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = note_token_program,
+    )]
+    pub note_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = note_mint,
+        associated_token::authority = buyer,
+        associated_token::token_program = note_token_program,
+    )]
+    pub buyer_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = seller_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = seller_stablecoin_vault.owner == seller.key(),
+    )]
+    pub seller_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = buyer_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = buyer_stablecoin_vault.owner == buyer.key(),
+    )]
+    pub buyer_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub seller: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub note_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct DelistLoan<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        close = seller,
+        has_one = seller,
+        has_one = order,
+        seeds = [
+            b"listing".as_ref(),
+            order.key().as_ref()
+        ],
+        bump = listing.nonce
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        address = order.note_mint,
+    )]
+    pub note_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = note_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = note_token_program,
+    )]
+    pub note_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = seller_note_account.mint == note_mint.key(),
+        constraint = seller_note_account.owner == seller.key(),
+    )]
+    pub seller_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub note_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct TransferBorrower<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = borrower,
+        // the nft of an escrowless order stays in the borrower's wallet
+        constraint = !order.is_escrowless,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub borrower: Signer<'info>,
+
+    pub new_borrower: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct SurrenderCollateral<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.borrower == borrower.key(),
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == lender.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(mut)]
+    pub lender: SystemAccount<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner != signer.key() @ ErrorCode::LoanListed,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Programmable nft accounts, only needed when the collateral is a pNFT.
+    pub pnft: ProgrammableNft<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct SurrenderSolCollateral<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        constraint = currency.stablecoin_vault == sol_vault.key(),
+        seeds = [
+            b"currency".as_ref(),
+            native_mint::ID.as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    #[account(mut)]
+    pub sol_vault: SystemAccount<'info>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.borrower == borrower.key(),
+        constraint = order.stablecoin_mint == native_mint::ID,
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == lender.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(mut)]
+    pub lender: SystemAccount<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner != signer.key() @ ErrorCode::LoanListed,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct SurrenderEscrowlessCollateral<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.borrower == borrower.key(),
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.is_escrowless,
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == order.borrower,
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: master edition of the nft, validated by Token Metadata against the mint
+    pub nft_edition: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == lender.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(mut)]
+    pub lender: SystemAccount<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner != signer.key() @ ErrorCode::LoanListed,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Program<'info, Token>,
+    pub token_metadata_program: Program<'info, Metadata>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ForgiveLoan<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.borrower == borrower.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub borrower: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Programmable nft accounts, only needed when the collateral is a pNFT.
+    pub pnft: ProgrammableNft<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner != signer.key() @ ErrorCode::LoanListed,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ForgiveSolLoan<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        constraint = currency.stablecoin_vault == sol_vault.key(),
+        seeds = [
+            b"currency".as_ref(),
+            native_mint::ID.as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    #[account(mut)]
+    pub sol_vault: SystemAccount<'info>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == native_mint::ID,
+        constraint = order.borrower == borrower.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: SystemAccount<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner != signer.key() @ ErrorCode::LoanListed,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ForgiveEscrowlessLoan<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.borrower == borrower.key(),
+        constraint = order.is_escrowless,
+        constraint = order.nft_vault == user_nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: master edition of the nft, validated by Token Metadata against the mint
+    pub nft_edition: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only the borrower can revoke the delegation of the thawed collateral.
+    pub borrower: Signer<'info>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner != signer.key() @ ErrorCode::LoanListed,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Program<'info, Token>,
+    pub token_metadata_program: Program<'info, Metadata>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ExtendDeadline<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub lender: Signer<'info>,
+
+    // Promissory note of the loan, held by the lender.
+    #[account(
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner == lender.key(),
+    )]
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ExpireOrder<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order, its rent goes back to the borrower.
+    #[account(
+        mut,
+        close = borrower,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.borrower == borrower.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: SystemAccount<'info>,
+
+    // Anyone may crank an expired order.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Programmable nft accounts, only needed when the collateral is a pNFT.
+    pub pnft: ProgrammableNft<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ExpireSolOrder<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        constraint = currency.stablecoin_vault == sol_vault.key(),
+        seeds = [
+            b"currency".as_ref(),
+            native_mint::ID.as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    #[account(mut)]
+    pub sol_vault: SystemAccount<'info>,
+
+    // Order, its rent goes back to the borrower.
+    #[account(
+        mut,
+        close = borrower,
+        constraint = order.stablecoin_mint == native_mint::ID,
+        constraint = order.borrower == borrower.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: SystemAccount<'info>,
+
+    // Anyone may crank an expired order.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ExpireEscrowlessOrder<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order, its rent goes back to the borrower.
+    #[account(
+        mut,
+        close = borrower,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.borrower == borrower.key(),
+        constraint = order.is_escrowless,
+        constraint = order.nft_vault == user_nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: master edition of the nft, validated by Token Metadata against the mint
+    pub nft_edition: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only the borrower can revoke the delegation of the thawed collateral.
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Program<'info, Token>,
+    pub token_metadata_program: Program<'info, Metadata>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct UpdateOrder<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = borrower,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub borrower: Signer<'info>,
+
+    /// CHECK: instructions sysvar, only needed when the market requires an appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, nonce: u8)]
+pub struct SetAllowedLenders<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = borrower,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        space = 8 + Allowlist::INIT_SPACE,
+        seeds = [
+            b"allowlist".as_ref(),
+            order.key().as_ref()
+        ],
+        bump
+    )]
+    pub allowlist: Box<Account<'info, Allowlist>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, nonce: u8)]
+pub struct MakeCounterOffer<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        has_one = stablecoin_mint,
+        constraint = order.borrower != lender.key(),
+        constraint = !order.is_perpetual @ ErrorCode::OpenTermNotSupported,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        init,
+        payer = lender,
+        space = 8 + CounterOffer::INIT_SPACE,
+        seeds = [
+            b"counter_offer".as_ref(),
+            order.key().as_ref(),
+            lender.key().as_ref()
+        ],
+        bump
+    )]
+    pub counter_offer: Box<Account<'info, CounterOffer>>,
+    #[account(
+        init,
+        payer = lender,
+        seeds = [
+            b"counter_offer_vault".as_ref(),
+            counter_offer.key().as_ref()
+        ],
+        bump,
+        token::mint = stablecoin_mint,
+        token::authority = signer,
+        token::token_program = token_program,
+    )]
+    pub offer_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Lenders the borrower restricted the order to, only needed for private orders.
+    #[account(
+        seeds = [
+            b"allowlist".as_ref(),
+            order.key().as_ref()
+        ],
+        bump = allowlist.nonce
+    )]
+    pub allowlist: Option<Box<Account<'info, Allowlist>>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct WithdrawCounterOffer<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    /// CHECK: only used to derive the counter-offer, which may outlive a closed order
+    #[account(
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump
+    )]
+    pub order: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = lender,
+        has_one = lender,
+        has_one = order,
+        seeds = [
+            b"counter_offer".as_ref(),
+            order.key().as_ref(),
+            lender.key().as_ref()
+        ],
+        bump = counter_offer.nonce
+    )]
+    pub counter_offer: Box<Account<'info, CounterOffer>>,
+    #[account(
+        mut,
+        seeds = [
+            b"counter_offer_vault".as_ref(),
+            counter_offer.key().as_ref()
+        ],
+        bump,
+    )]
+    pub offer_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = stablecoin_mint.key() == offer_vault.mint,
+    )]
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct AcceptCounterOffer<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = borrower,
+        has_one = stablecoin_mint,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        close = lender,
+        has_one = lender,
+        has_one = order,
+        seeds = [
+            b"counter_offer".as_ref(),
+            order.key().as_ref(),
+            lender.key().as_ref()
+        ],
+        bump = counter_offer.nonce
+    )]
+    pub counter_offer: Box<Account<'info, CounterOffer>>,
+    #[account(
+        mut,
+        seeds = [
+            b"counter_offer_vault".as_ref(),
+            counter_offer.key().as_ref()
+        ],
+        bump,
+    )]
+    pub offer_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = borrower_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = borrower_stablecoin_vault.owner == order.borrower,
+        constraint = borrower_stablecoin_vault.key() == order.stablecoin_vault,
+    )]
+    pub borrower_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(mut)]
+    pub lender: SystemAccount<'info>,
+
+    // Promissory note minted to the lender.
+    #[account(
+        init,
+        payer = borrower,
+        seeds = [
+            b"note".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+        mint::decimals = 0,
+        mint::authority = signer,
+        mint::freeze_authority = signer,
+        mint::token_program = note_token_program,
+    )]
+    pub note_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = borrower,
+        associated_token::mint = note_mint,
+        associated_token::authority = lender,
+        associated_token::token_program = note_token_program,
+    )]
+    pub lender_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    // Token Metadata accounts of the promissory note.
+    pub note_metadata: NoteMetadata<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    /// CHECK: instructions sysvar, only needed when the market requires an appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub note_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8, bitmap_nonce: u8, nonce_page: u64)]
+pub struct FillSignedOrder<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        has_one = stablecoin_mint,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = borrower_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = borrower_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub borrower_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Borrower's nft account, the market signer must be its approved delegate.
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Order.
+    #[account(
+        init,
+        payer = lender,
+        space = 8 + Order::INIT_SPACE,
+        seeds = [
+            nft_collaterized_loans.order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    // Nonces of the borrower's signed orders already filled.
+    #[account(
+        init_if_needed,
+        payer = lender,
+        space = 8 + OrderNonces::INIT_SPACE,
+        seeds = [
+            b"order_nonces".as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref(),
+            borrower.key().as_ref(),
+            nonce_page.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub order_nonces: Box<Account<'info, OrderNonces>>,
+
+    pub borrower: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    // Promissory note minted to the lender.
+    #[account(
+        init,
+        payer = lender,
+        seeds = [
+            b"note".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+        mint::decimals = 0,
+        mint::authority = signer,
+        mint::freeze_authority = signer,
+        mint::token_program = note_token_program,
+    )]
+    pub note_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = lender,
+        associated_token::mint = note_mint,
+        associated_token::authority = lender,
+        associated_token::token_program = note_token_program,
+    )]
+    pub lender_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    // Token Metadata accounts of the promissory note.
+    pub note_metadata: NoteMetadata<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    /// CHECK: instructions sysvar holding the borrower's signature
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>,
+    pub note_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct Contribute<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        constraint = order.borrower != lender.key(),
+        // syndicated collateral moves through plain token transfers
+        constraint = !order.is_programmable && !order.is_escrowless,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        init_if_needed,
+        payer = lender,
+        seeds = [
+            b"syndicate_vault".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+        token::mint = stablecoin_mint,
+        token::authority = signer,
+        token::token_program = token_program,
+    )]
+    pub syndicate_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Contribution of the lender to the order.
+    #[account(
+        init_if_needed,
+        payer = lender,
+        space = 8 + LoanShare::INIT_SPACE,
+        seeds = [
+            b"share".as_ref(),
+            order.key().as_ref(),
+            lender.key().as_ref()
+        ],
+        bump
+    )]
+    pub share: Box<Account<'info, LoanShare>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = borrower_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = borrower_stablecoin_vault.owner == order.borrower,
+        constraint = borrower_stablecoin_vault.key() == order.stablecoin_vault,
+    )]
+    pub borrower_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Lenders the borrower restricted the order to, only needed for private orders.
+    #[account(
+        seeds = [
+            b"allowlist".as_ref(),
+            order.key().as_ref()
+        ],
+        bump = allowlist.nonce
+    )]
+    pub allowlist: Option<Box<Account<'info, Allowlist>>>,
+
+    /// CHECK: instructions sysvar, only needed when the market requires an appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct WithdrawContribution<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"syndicate_vault".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+    )]
+    pub syndicate_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = lender,
+        has_one = lender,
+        has_one = order,
+        seeds = [
+            b"share".as_ref(),
+            order.key().as_ref(),
+            lender.key().as_ref()
+        ],
+        bump = share.nonce
+    )]
+    pub share: Box<Account<'info, LoanShare>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PaybackSyndicated<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.is_syndicated,
+        constraint = order.borrower == borrower.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"syndicate_vault".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+    )]
+    pub syndicate_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = payer_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = payer_stablecoin_vault.owner == payer.key(),
+    )]
+    pub payer_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Collateral is returned to the borrower, who need not sign.
+    pub borrower: SystemAccount<'info>,
+
+    // Repays the loan, may be anyone.
+    pub payer: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct BidCollateral<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        constraint = order.is_syndicated,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"syndicate_vault".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+    )]
+    pub syndicate_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = bidder_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = bidder_stablecoin_vault.owner == bidder.key(),
+    )]
+    pub bidder_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Refunded when outbid, only needed once there is a bid.
+    #[account(
+        mut,
+        constraint = previous_bidder_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = previous_bidder_stablecoin_vault.owner == order.highest_bidder,
+    )]
+    pub previous_bidder_stablecoin_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub bidder: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct SettleAuction<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.is_syndicated,
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"syndicate_vault".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+    )]
+    pub syndicate_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
+    )]
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = winner_nft_vault.mint == nft_mint.key(),
+        constraint = winner_nft_vault.owner == order.highest_bidder,
+    )]
+    pub winner_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct ClaimShare<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
+    #[account(
+        has_one = stablecoin_mint,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"syndicate_vault".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+    )]
+    pub syndicate_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = lender,
+        has_one = lender,
+        has_one = order,
+        seeds = [
+            b"share".as_ref(),
+            order.key().as_ref(),
+            lender.key().as_ref()
+        ],
+        bump = share.nonce
+    )]
+    pub share: Box<Account<'info, LoanShare>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8, collection: Pubkey)]
+pub struct CreatePool<'info> {
+    #[account(has_one = authority)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        has_one = stablecoin_mint,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + LendingPool::INIT_SPACE,
+        seeds = [
+            b"pool".as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref(),
+            collection.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [
+            b"pool_vault".as_ref(),
+            pool.key().as_ref()
+        ],
+        bump,
+        token::mint = stablecoin_mint,
+        token::authority = signer,
+        token::token_program = token_program,
+    )]
+    pub pool_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // LP shares of the pool, priced like the loan currency.
+    #[account(
+        init,
+        payer = authority,
+        seeds = [
+            b"lp_mint".as_ref(),
+            pool.key().as_ref()
+        ],
+        bump,
+        mint::decimals = stablecoin_mint.decimals,
+        mint::authority = signer,
+        mint::token_program = lp_token_program,
+    )]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub lp_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, junior: bool)]
+pub struct Deposit<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = pool_vault,
+        seeds = [
+            b"pool".as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref(),
+            pool.collection.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump = pool.nonce
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub pool_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // LP mint of the tranche
+    #[account(
+        mut,
+        constraint = lp_mint.key() == pool.lp_mint_of(junior),
+    )]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = lender,
+        associated_token::mint = lp_mint,
+        associated_token::authority = lender,
+        associated_token::token_program = lp_token_program,
+    )]
+    pub lender_lp_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub lp_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+}
+
+#[derive(Accounts)]
+#[instruction(shares: u64, junior: bool)]
+pub struct Withdraw<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = pool_vault,
+        seeds = [
+            b"pool".as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref(),
+            pool.collection.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump = pool.nonce
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub pool_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // LP mint of the tranche
+    #[account(
+        mut,
+        constraint = lp_mint.key() == pool.lp_mint_of(junior),
+    )]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = lender_lp_account.mint == lp_mint.key(),
+        constraint = lender_lp_account.owner == lender.key(),
+    )]
+    pub lender_lp_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub lender: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>,
+    pub lp_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+pub struct BorrowFromPool<'info> {
+    #[account(mut)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = pool_vault,
+        seeds = [
+            b"pool".as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref(),
+            pool.collection.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump = pool.nonce
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub pool_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    // Senior LP mint, prices the shares waiting in the withdrawal queue.
+    #[account(address = pool.lp_mint)]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = user_stablecoin_vault.owner == borrower.key(),
+    )]
+    pub user_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Metadata of the nft, checked for the pool's collection.
+    #[account(
+        constraint = nft_metadata.mint == nft_mint.key() @ ErrorCode::InvalidNftMetadata,
+    )]
+    pub nft_metadata: Box<Account<'info, MetadataAccount>>,
+
+    // Order.
+    #[account(
+        init,
+        payer = borrower,
+        space = 8 + Order::INIT_SPACE,
+        seeds = [
+            nft_collaterized_loans.order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    /// CHECK: instructions sysvar holding the appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct RepayPoolLoan<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = pool_vault,
+        seeds = [
+            b"pool".as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref(),
+            pool.collection.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump = pool.nonce
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = pool,
+        constraint = order.borrower == borrower.key(),
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub pool_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = payer_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = payer_stablecoin_vault.owner == payer.key(),
+    )]
+    pub payer_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_nft_vault.mint == nft_mint.key(),
+        constraint = user_nft_vault.owner == borrower.key(),
+    )]
+    pub user_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Collateral is returned to the borrower, who need not sign.
+    pub borrower: SystemAccount<'info>,
+
+    // Repays the loan, may be anyone.
+    pub payer: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct SetCollateralPrice<'info> {
+    #[account(has_one = authority)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        constraint = pool.market == nft_collaterized_loans.key(),
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = pool,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: instructions sysvar, only needed when the market requires an appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct BuyPoolCollateral<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = pool_vault,
+        seeds = [
+            b"pool".as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref(),
+            pool.collection.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump = pool.nonce
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    // Order.
+    #[account(
+        mut,
+        has_one = pool,
+        constraint = order.nft_vault == nft_vault.key(),
+        constraint = order.nft_mint == nft_mint.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub pool_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = buyer_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = buyer_stablecoin_vault.owner == buyer.key(),
+    )]
+    pub buyer_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.supply == 1,
+        constraint = nft_mint.decimals == 0,
+    )]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = nft_vault.mint == nft_mint.key(),
+        constraint = nft_vault.owner == signer.key(),
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = buyer_nft_vault.mint == nft_mint.key(),
+        constraint = buyer_nft_vault.owner == buyer.key(),
+    )]
+    pub buyer_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub buyer: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+pub struct SetRateModel<'info> {
+    #[account(has_one = authority)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        constraint = pool.market == nft_collaterized_loans.key(),
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QueueWithdrawal<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = pool_vault,
+        has_one = lp_mint,
+        seeds = [
+            b"pool".as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref(),
+            pool.collection.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump = pool.nonce
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    #[account(
+        init_if_needed,
+        payer = lender,
+        space = 8 + WithdrawalQueue::INIT_SPACE,
+        seeds = [
+            b"withdrawal_queue".as_ref(),
+            pool.key().as_ref()
+        ],
+        bump
+    )]
+    pub withdrawal_queue: Box<Account<'info, WithdrawalQueue>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: only its key is stored on the pool
+    pub pool_vault: UncheckedAccount<'info>,
+
+    // Paid out when the withdrawal is processed.
+    #[account(
+        associated_token::mint = stablecoin_mint,
+        associated_token::authority = lender,
+        associated_token::token_program = token_program,
+    )]
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = lender_lp_account.mint == lp_mint.key(),
+        constraint = lender_lp_account.owner == lender.key(),
+    )]
+    pub lender_lp_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = lender,
+        seeds = [
+            b"queue_lp_vault".as_ref(),
+            pool.key().as_ref()
+        ],
+        bump,
+        token::mint = lp_mint,
+        token::authority = signer,
+        token::token_program = lp_token_program,
+    )]
+    pub queue_lp_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub lp_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+pub struct ProcessWithdrawalQueue<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = pool_vault,
+        has_one = lp_mint,
+        seeds = [
+            b"pool".as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref(),
+            pool.collection.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump = pool.nonce
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    #[account(
+        mut,
+        has_one = pool,
+        seeds = [
+            b"withdrawal_queue".as_ref(),
+            pool.key().as_ref()
+        ],
+        bump = withdrawal_queue.nonce
+    )]
+    pub withdrawal_queue: Box<Account<'info, WithdrawalQueue>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub pool_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: lender at the head of the queue, only owns the payout account
+    #[account(
+        constraint = withdrawal_queue.entries.first().map(|entry| entry.lender) == Some(lender.key()),
+    )]
+    pub lender: UncheckedAccount<'info>,
+    // Payout account of the head of the queue, recreated if the lender closed it.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = stablecoin_mint,
+        associated_token::authority = lender,
+        associated_token::token_program = token_program,
+    )]
+    pub payout_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            b"queue_lp_vault".as_ref(),
+            pool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub queue_lp_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // Anyone may crank the queue.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub lp_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
+}
+
+#[derive(Accounts)]
+pub struct EnableJuniorTranche<'info> {
+    #[account(has_one = authority)]
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        constraint = pool.market == nft_collaterized_loans.key(),
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // LP shares of the first-loss tranche.
+    #[account(
+        init,
+        payer = authority,
+        seeds = [
+            b"junior_lp_mint".as_ref(),
+            pool.key().as_ref()
+        ],
+        bump,
+        mint::decimals = stablecoin_mint.decimals,
+        mint::authority = signer,
+        mint::token_program = lp_token_program,
+    )]
+    pub junior_lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub lp_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct CreateStrategy<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Strategy::INIT_SPACE,
+        seeds = [
+            b"strategy".as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref(),
+            owner.key().as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump
+    )]
+    pub strategy: Box<Account<'info, Strategy>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    // Escrow of the strategy, also where repayments of its loans arrive.
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            b"strategy_vault".as_ref(),
+            strategy.key().as_ref()
+        ],
+        bump,
+        token::mint = stablecoin_mint,
+        token::authority = strategy,
+        token::token_program = token_program,
+    )]
+    pub strategy_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+pub struct FundStrategy<'info> {
+    #[account(
+        has_one = owner,
+        has_one = stablecoin_mint,
+        has_one = strategy_vault,
+        seeds = [
+            b"strategy".as_ref(),
+            strategy.market.as_ref(),
+            strategy.owner.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump = strategy.nonce
+    )]
+    pub strategy: Box<Account<'info, Strategy>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub strategy_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
         mut,
-        has_one = stablecoin_vault,
-        has_one = stablecoin_mint
+        constraint = owner_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = owner_stablecoin_vault.owner == owner.key(),
+    )]
+    pub owner_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub owner: Signer<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromStrategy<'info> {
+    #[account(
+        has_one = owner,
+        has_one = stablecoin_mint,
+        has_one = strategy_vault,
+        seeds = [
+            b"strategy".as_ref(),
+            strategy.market.as_ref(),
+            strategy.owner.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump = strategy.nonce
+    )]
+    pub strategy: Box<Account<'info, Strategy>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub strategy_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = owner_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = owner_stablecoin_vault.owner == owner.key(),
+    )]
+    pub owner_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub owner: Signer<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+pub struct WithdrawStrategyNote<'info> {
+    #[account(
+        has_one = owner,
+        seeds = [
+            b"strategy".as_ref(),
+            strategy.market.as_ref(),
+            strategy.owner.as_ref(),
+            strategy.stablecoin_mint.as_ref()
+        ],
+        bump = strategy.nonce
+    )]
+    pub strategy: Box<Account<'info, Strategy>>,
+
+    pub note_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = strategy_note_account.mint == note_mint.key(),
+        constraint = strategy_note_account.owner == strategy.key(),
     )]
+    pub strategy_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = owner_note_account.mint == note_mint.key(),
+        constraint = owner_note_account.owner == owner.key(),
+    )]
+    pub owner_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub owner: Signer<'info>,
+
+    // misc
+    pub note_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct Relend<'info> {
     pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
 
     // Order.
     #[account(
         mut,
-        constraint = order.stablecoin_vault == stablecoin_vault.key(),
-        constraint = order.borrower != lender.key(),
+        has_one = stablecoin_mint,
+        constraint = order.borrower != strategy.owner,
         seeds = [
             order_id.to_string().as_ref(),
             nft_collaterized_loans.to_account_info().key().as_ref()
@@ -472,27 +8429,76 @@ pub struct GiveLoan<'info> {
     )]
     pub order: Box<Account<'info, Order>>,
 
-    pub stablecoin_mint: Box<Account<'info, Mint>>,
     #[account(
-        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = stablecoin_vault.owner == signer.key(),
+        constraint = strategy.market == nft_collaterized_loans.key(),
+        has_one = stablecoin_mint,
+        has_one = strategy_vault,
+        seeds = [
+            b"strategy".as_ref(),
+            strategy.market.as_ref(),
+            strategy.owner.as_ref(),
+            stablecoin_mint.key().as_ref()
+        ],
+        bump = strategy.nonce
     )]
-    pub stablecoin_vault: Box<Account<'info, TokenAccount>>,
+    pub strategy: Box<Account<'info, Strategy>>,
 
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub strategy_vault: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
-        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = lender_stablecoin_vault.owner == lender.key(),
-    )]
-    pub lender_stablecoin_vault: Box<Account<'info, TokenAccount>>,
-    #[account(
+        mut,
         constraint = borrower_stablecoin_vault.mint == stablecoin_mint.key(),
         constraint = borrower_stablecoin_vault.owner == order.borrower,
+        constraint = borrower_stablecoin_vault.key() == order.stablecoin_vault,
+    )]
+    pub borrower_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Metadata of the collateral, checked against the strategy's collections.
+    #[account(
+        constraint = nft_metadata.mint == order.nft_mint @ ErrorCode::InvalidNftMetadata,
     )]
-    pub borrower_stablecoin_vault: Box<Account<'info, TokenAccount>>,
+    pub nft_metadata: Box<Account<'info, MetadataAccount>>,
 
+    // Collateral of the order, escrowless collateral must still be frozen to the market.
+    #[account(
+        constraint = nft_vault.key() == order.nft_vault,
+        constraint = nft_vault.amount == 1 @ ErrorCode::CollateralReleased,
+        constraint = !order.is_escrowless || (nft_vault.is_frozen() && nft_vault.delegate == COption::Some(signer.key())) @ ErrorCode::CollateralReleased,
+    )]
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Promissory note minted to the strategy.
+    #[account(
+        init,
+        payer = payer,
+        seeds = [
+            b"note".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
+        mint::decimals = 0,
+        mint::authority = signer,
+        mint::freeze_authority = signer,
+        mint::token_program = note_token_program,
+    )]
+    pub note_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = note_mint,
+        associated_token::authority = strategy,
+        associated_token::token_program = note_token_program,
+    )]
+    pub strategy_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    // Token Metadata accounts of the promissory note.
+    pub note_metadata: NoteMetadata<'info>,
+
+    // Anyone may crank the strategy.
     #[account(mut)]
-    pub lender: Signer<'info>,
+    pub payer: Signer<'info>,
 
+    /// CHECK: market PDA owning the vaults, verified by seeds
     #[account(
         seeds = [
             nft_collaterized_loans.to_account_info().key.as_ref()
@@ -501,28 +8507,45 @@ pub struct GiveLoan<'info> {
     )]
     pub signer: UncheckedAccount<'info>,
 
+    /// CHECK: instructions sysvar, only needed when the market requires an appraisal
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
     // misc
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>
+    pub token_program: Interface<'info, TokenInterface>,
+    pub note_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
 }
 
 #[derive(Accounts)]
 #[instruction(order_id: u64)]
-pub struct Payback<'info> {
+pub struct MakePerpetual<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
     #[account(
         mut,
-        has_one = stablecoin_vault,
-        has_one = stablecoin_mint
+        has_one = borrower,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
     )]
+    pub order: Box<Account<'info, Order>>,
+
+    pub borrower: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct CallLoan<'info> {
     pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
 
     // Order.
     #[account(
         mut,
-        constraint = order.stablecoin_vault == stablecoin_vault.key(),
-        constraint = order.borrower == borrower.key(),
-        constraint = order.nft_vault == nft_vault.key(),
-        constraint = order.nft_mint == nft_mint.key(),
         seeds = [
             order_id.to_string().as_ref(),
             nft_collaterized_loans.to_account_info().key().as_ref()
@@ -531,46 +8554,94 @@ pub struct Payback<'info> {
     )]
     pub order: Box<Account<'info, Order>>,
 
-    pub stablecoin_mint: Box<Account<'info, Mint>>,
+    pub lender: Signer<'info>,
+
+    // Promissory note of the loan, held by the lender.
     #[account(
-        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = stablecoin_vault.owner == signer.key(),
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner == lender.key(),
     )]
-    pub stablecoin_vault: Box<Account<'info, TokenAccount>>,
-    
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct Refinance<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    // Order.
     #[account(
-        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = lender_stablecoin_vault.owner == order.lender,
+        mut,
+        has_one = stablecoin_mint,
+        constraint = order.borrower != lender.key(),
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
     )]
-    pub lender_stablecoin_vault: Box<Account<'info, TokenAccount>>,
+    pub order: Box<Account<'info, Order>>,
 
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
-        constraint = user_stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = user_stablecoin_vault.owner == borrower.key(),
+        mut,
+        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = lender_stablecoin_vault.owner == lender.key(),
     )]
-    pub user_stablecoin_vault: Box<Account<'info, TokenAccount>>,
-    
+    pub lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
-        constraint = nft_mint.supply == 1,
-        constraint = nft_mint.decimals == 0,
+        constraint = old_lender_stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = old_lender_stablecoin_vault.owner == note_account.owner,
     )]
-    pub nft_mint: Box<Account<'info, Mint>>,
+    pub old_lender_stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Promissory note of the loan, its holder is paid off.
     #[account(
-        constraint = nft_vault.mint == nft_mint.key(),
-        constraint = nft_vault.owner == signer.key(),
+        constraint = note_account.mint == order.note_mint,
+        constraint = note_account.amount == 1,
+        constraint = note_account.owner != signer.key() @ ErrorCode::LoanListed,
     )]
-    pub nft_vault: Box<Account<'info, TokenAccount>>,
+    pub note_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    /// CHECK: holder of the note, checked for a strategy in the handler
     #[account(
-        constraint = user_nft_vault.mint == nft_mint.key(),
-        constraint = user_nft_vault.owner == borrower.key(),
+        address = note_account.owner,
     )]
-    pub user_nft_vault: Box<Account<'info, TokenAccount>>,
+    pub note_holder: UncheckedAccount<'info>,
 
     #[account(mut)]
-    pub borrower: Signer<'info>,
+    pub lender: Signer<'info>,
+
+    // Promissory note minted to the new lender, one per call of the loan.
+    #[account(
+        init,
+        payer = lender,
+        seeds = [
+            b"note".as_ref(),
+            order.key().as_ref(),
+            order.called_at.to_le_bytes().as_ref()
+        ],
+        bump,
+        mint::decimals = 0,
+        mint::authority = signer,
+        mint::freeze_authority = signer,
+        mint::token_program = note_token_program,
+    )]
+    pub note_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = lender,
+        associated_token::mint = note_mint,
+        associated_token::authority = lender,
+        associated_token::token_program = note_token_program,
+    )]
+    pub lender_note_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    // Token Metadata accounts of the promissory note.
+    pub note_metadata: NoteMetadata<'info>,
 
+    /// CHECK: market PDA owning the vaults, verified by seeds
     #[account(
         seeds = [
             nft_collaterized_loans.to_account_info().key.as_ref()
@@ -581,24 +8652,94 @@ pub struct Payback<'info> {
 
     // misc
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>
+    pub token_program: Interface<'info, TokenInterface>,
+    pub note_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>
 }
 
 #[derive(Accounts)]
-#[instruction(order_id: u64)]
-pub struct Liquidate<'info> {
+pub struct SetRefinanceConfig<'info> {
     #[account(
         mut,
-        has_one = stablecoin_vault,
-        has_one = stablecoin_mint
+        has_one = authority
     )]
     pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
 
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, claimant: Pubkey)]
+pub struct VoteClaim<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
     // Order.
+    #[account(
+        constraint = order.is_syndicated,
+        seeds = [
+            order_id.to_string().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = order.nonce
+    )]
+    pub order: Box<Account<'info, Order>>,
+
     #[account(
         mut,
-        constraint = order.stablecoin_vault == stablecoin_vault.key(),
         has_one = lender,
+        has_one = order,
+        seeds = [
+            b"share".as_ref(),
+            order.key().as_ref(),
+            lender.key().as_ref()
+        ],
+        bump = share.nonce
+    )]
+    pub share: Box<Account<'info, LoanShare>>,
+
+    #[account(
+        init_if_needed,
+        payer = lender,
+        space = 8 + ClaimVote::INIT_SPACE,
+        seeds = [
+            b"claim_vote".as_ref(),
+            order.key().as_ref(),
+            claimant.as_ref()
+        ],
+        bump
+    )]
+    pub claim_vote: Box<Account<'info, ClaimVote>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    // misc
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct SettleClaim<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = stablecoin_mint,
+        has_one = stablecoin_vault,
+        seeds = [
+            b"currency".as_ref(),
+            stablecoin_mint.key().as_ref(),
+            nft_collaterized_loans.to_account_info().key().as_ref()
+        ],
+        bump = currency.nonce
+    )]
+    pub currency: Box<Account<'info, Currency>>,
+
+    // Order.
+    #[account(
+        mut,
+        constraint = order.stablecoin_mint == stablecoin_mint.key(),
+        constraint = order.is_syndicated,
         constraint = order.nft_vault == nft_vault.key(),
         constraint = order.nft_mint == nft_mint.key(),
         seeds = [
@@ -609,40 +8750,111 @@ pub struct Liquidate<'info> {
     )]
     pub order: Box<Account<'info, Order>>,
 
-    pub stablecoin_mint: Box<Account<'info, Mint>>,
     #[account(
-        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = stablecoin_vault.owner == signer.key(),
+        mut,
+        seeds = [
+            b"syndicate_vault".as_ref(),
+            order.key().as_ref()
+        ],
+        bump,
     )]
-    pub stablecoin_vault: Box<Account<'info, TokenAccount>>,
-    
+    pub syndicate_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub stablecoin_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
-        constraint = lender_stablecoin_vault.mint == stablecoin_mint.key(),
-        constraint = lender_stablecoin_vault.owner == lender.key(),
+        mut,
+        constraint = stablecoin_vault.mint == stablecoin_mint.key(),
+        constraint = stablecoin_vault.owner == signer.key(),
     )]
-    pub lender_stablecoin_vault: Box<Account<'info, TokenAccount>>,
-    
+    pub stablecoin_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
     #[account(
         mut,
         constraint = nft_mint.supply == 1,
         constraint = nft_mint.decimals == 0,
     )]
-    pub nft_mint: Box<Account<'info, Mint>>,
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
+        mut,
         constraint = nft_vault.mint == nft_mint.key(),
         constraint = nft_vault.owner == signer.key(),
     )]
-    pub nft_vault: Box<Account<'info, TokenAccount>>,
-
+    pub nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        has_one = order,
+        seeds = [
+            b"claim_vote".as_ref(),
+            order.key().as_ref(),
+            claim_vote.claimant.as_ref()
+        ],
+        bump = claim_vote.nonce
+    )]
+    pub claim_vote: Box<Account<'info, ClaimVote>>,
+
+    #[account(
+        mut,
+        constraint = claimant_nft_vault.mint == nft_mint.key(),
+        constraint = claimant_nft_vault.owner == claim_vote.claimant,
+    )]
+    pub claimant_nft_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: market PDA owning the vaults, verified by seeds
+    #[account(
+        seeds = [
+            nft_collaterized_loans.to_account_info().key.as_ref()
+        ],
+        bump = nft_collaterized_loans.nonce,
+    )]
+    pub signer: UncheckedAccount<'info>,
+
+    // misc
+    pub token_program: Interface<'info, TokenInterface>,
+    pub nft_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    pub nft_collaterized_loans: Box<Account<'info, NFTCollaterizedLoans>>,
+
+    #[account(
+        mut,
+        has_one = lp_mint,
+        constraint = pool.market == nft_collaterized_loans.key(),
+    )]
+    pub pool: Box<Account<'info, LendingPool>>,
+
+    #[account(
+        mut,
+        has_one = pool,
+        seeds = [
+            b"withdrawal_queue".as_ref(),
+            pool.key().as_ref()
+        ],
+        bump = withdrawal_queue.nonce
+    )]
+    pub withdrawal_queue: Box<Account<'info, WithdrawalQueue>>,
+
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = lender_lp_account.mint == lp_mint.key(),
+        constraint = lender_lp_account.owner == lender.key(),
+    )]
+    pub lender_lp_account: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
-        constraint = user_nft_vault.mint == nft_mint.key(),
-        constraint = user_nft_vault.owner == lender.key(),
+        mut,
+        seeds = [
+            b"queue_lp_vault".as_ref(),
+            pool.key().as_ref()
+        ],
+        bump,
     )]
-    pub user_nft_vault: Box<Account<'info, TokenAccount>>,
+    pub queue_lp_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    #[account(mut)]
     pub lender: Signer<'info>,
 
+    /// CHECK: market PDA owning the vaults, verified by seeds
     #[account(
         seeds = [
             nft_collaterized_loans.to_account_info().key.as_ref()
@@ -652,32 +8864,366 @@ pub struct Liquidate<'info> {
     pub signer: UncheckedAccount<'info>,
 
     // misc
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>
+    pub lp_token_program: Interface<'info, TokenInterface>
+}
+
+#[derive(Accounts)]
+pub struct ProgrammableNft<'info> {
+    #[account(mut)]
+    pub nft_metadata: Option<Box<Account<'info, MetadataAccount>>>,
+    /// CHECK: master edition of the nft, checked by Token Metadata
+    pub nft_edition: Option<UncheckedAccount<'info>>,
+    /// CHECK: token record of the source token account, checked by Token Metadata
+    #[account(mut)]
+    pub owner_token_record: Option<UncheckedAccount<'info>>,
+    /// CHECK: token record of the destination token account, checked by Token Metadata
+    #[account(mut)]
+    pub destination_token_record: Option<UncheckedAccount<'info>>,
+    /// CHECK: rule set of the nft, checked by Token Metadata
+    pub authorization_rules: Option<UncheckedAccount<'info>>,
+    /// CHECK: Token Auth Rules program, checked by Token Metadata
+    pub authorization_rules_program: Option<UncheckedAccount<'info>>,
+    pub token_metadata_program: Option<Program<'info, Metadata>>,
+    /// CHECK: instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: Option<UncheckedAccount<'info>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+impl<'info> ProgrammableNft<'info> {
+    // Whether the collateral is a pNFT and has to move through Token Metadata.
+    pub fn is_programmable(&self, nft_mint: &Pubkey) -> Result<bool> {
+        match &self.nft_metadata {
+            Some(nft_metadata) => {
+                if nft_metadata.mint != *nft_mint {
+                    return Err(ErrorCode::InvalidNftMetadata.into());
+                }
+                Ok(nft_metadata.token_standard == Some(TokenStandard::ProgrammableNonFungible))
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Token Metadata Transfer of a pNFT, keeping its token records and rule set in sync.
+    #[allow(clippy::too_many_arguments)]
+    pub fn transfer(
+        &self,
+        token: AccountInfo<'info>,
+        token_owner: AccountInfo<'info>,
+        destination_token: AccountInfo<'info>,
+        destination_owner: AccountInfo<'info>,
+        mint: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        payer: AccountInfo<'info>,
+        system_program: AccountInfo<'info>,
+        spl_token_program: AccountInfo<'info>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let missing = || error!(ErrorCode::MissingProgrammableNftAccounts);
+        let metadata = self.nft_metadata.as_ref().ok_or_else(missing)?.to_account_info();
+        let edition = self.nft_edition.as_ref().ok_or_else(missing)?.to_account_info();
+        let owner_token_record = self.owner_token_record.as_ref().ok_or_else(missing)?.to_account_info();
+        let destination_token_record = self.destination_token_record.as_ref().ok_or_else(missing)?.to_account_info();
+        let token_metadata_program = self.token_metadata_program.as_ref().ok_or_else(missing)?.to_account_info();
+        let sysvar_instructions = self.sysvar_instructions.as_ref().ok_or_else(missing)?.to_account_info();
+        let associated_token_program = self.associated_token_program.as_ref().ok_or_else(missing)?.to_account_info();
+        let authorization_rules = self.authorization_rules.as_ref().map(|a| a.to_account_info());
+        let authorization_rules_program = self.authorization_rules_program.as_ref().map(|a| a.to_account_info());
+
+        TransferV1CpiBuilder::new(&token_metadata_program)
+            .token(&token)
+            .token_owner(&token_owner)
+            .destination_token(&destination_token)
+            .destination_owner(&destination_owner)
+            .mint(&mint)
+            .metadata(&metadata)
+            .edition(Some(&edition))
+            .token_record(Some(&owner_token_record))
+            .destination_token_record(Some(&destination_token_record))
+            .authority(&authority)
+            .payer(&payer)
+            .system_program(&system_program)
+            .sysvar_instructions(&sysvar_instructions)
+            .spl_token_program(&spl_token_program)
+            .spl_ata_program(&associated_token_program)
+            .authorization_rules_program(authorization_rules_program.as_ref())
+            .authorization_rules(authorization_rules.as_ref())
+            .amount(1)
+            .invoke_signed(signer_seeds)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct NoteMetadata<'info> {
+    /// CHECK: metadata of the promissory note, derived and created by Token Metadata
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: master edition of the promissory note, derived and created by Token Metadata
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+}
+
+impl<'info> NoteMetadata<'info> {
+    // Creates the metadata and master edition of a freshly minted note, making it a tradeable nft.
+    // The master edition takes over the mint authority, so nobody can mint a second note.
+    pub fn create(
+        &self,
+        note_mint: AccountInfo<'info>,
+        signer: AccountInfo<'info>,
+        payer: AccountInfo<'info>,
+        system_program: AccountInfo<'info>,
+        note_token_program: AccountInfo<'info>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let token_metadata_program = self.token_metadata_program.to_account_info();
+        let metadata = self.metadata.to_account_info();
+        let master_edition = self.master_edition.to_account_info();
+
+        CreateMetadataAccountV3CpiBuilder::new(&token_metadata_program)
+            .metadata(&metadata)
+            .mint(&note_mint)
+            .mint_authority(&signer)
+            .payer(&payer)
+            .update_authority(&signer, true)
+            .system_program(&system_program)
+            .data(DataV2 {
+                name: NOTE_NAME.to_string(),
+                symbol: NOTE_SYMBOL.to_string(),
+                uri: String::new(),
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            })
+            .is_mutable(false)
+            .invoke_signed(signer_seeds)?;
+
+        CreateMasterEditionV3CpiBuilder::new(&token_metadata_program)
+            .edition(&master_edition)
+            .mint(&note_mint)
+            .update_authority(&signer)
+            .mint_authority(&signer)
+            .payer(&payer)
+            .metadata(&metadata)
+            .token_program(&note_token_program)
+            .system_program(&system_program)
+            .max_supply(0)
+            .invoke_signed(signer_seeds)?;
+
+        Ok(())
+    }
 }
 
 #[account]
+#[derive(InitSpace)]
 pub struct NFTCollaterizedLoans {
+    // admin allowed to manage the currency allowlist
+    pub authority: Pubkey,
+    // latest order id
+    pub order_id: u64,
+    // key signing price attestations, default key when appraisals are not required
+    pub appraiser: Pubkey,
+    // maximum loan-to-value of an appraised order in basis points
+    pub max_ltv_bps: u16,
+    // maximum age of a price attestation in seconds
+    pub max_appraisal_age: u64,
+    // health in basis points below which a lender may issue a margin call, 0 disables margin calls
+    pub margin_call_threshold_bps: u16,
+    // time the borrower has to answer a margin call
+    pub margin_call_period: u64,
+    // annual rate in basis points the refinance auction of a called loan ramps up to
+    pub max_refinance_rate_bps: u64,
+
+    // nonce 
+    pub nonce: u8
+}
+
+impl NFTCollaterizedLoans {
+    // Enforces the maximum loan-to-value against a signed appraisal when the market requires one.
+    pub fn check_loan_to_value(
+        &self,
+        instructions_sysvar: Option<&UncheckedAccount>,
+        nft_mint: &Pubkey,
+        stablecoin_mint: &Pubkey,
+        request_amount: u64,
+        additional_collateral: u64,
+    ) -> Result<()> {
+        if self.appraiser == Pubkey::default() {
+            return Ok(());
+        }
+
+        let price = self.appraise(instructions_sysvar, nft_mint, stablecoin_mint)?;
+        let max_loan = (price as u128) * (self.max_ltv_bps as u128) / (BPS_DENOMINATOR as u128);
+        if request_amount.saturating_sub(additional_collateral) as u128 > max_loan {
+            return Err(ErrorCode::LoanToValueExceeded.into());
+        }
+
+        Ok(())
+    }
+
+    // Price of the nft from a fresh appraisal signed by the market appraiser.
+    pub fn appraise(&self, instructions_sysvar: Option<&UncheckedAccount>, nft_mint: &Pubkey, stablecoin_mint: &Pubkey) -> Result<u64> {
+        if self.appraiser == Pubkey::default() {
+            return Err(ErrorCode::MissingAppraisal.into());
+        }
+
+        let instructions_sysvar = instructions_sysvar.ok_or(ErrorCode::MissingAppraisal)?;
+        let appraisal = Appraisal::load(&instructions_sysvar.to_account_info(), &self.appraiser, nft_mint, stablecoin_mint)?;
+
+        let now = clock::Clock::get()?.unix_timestamp;
+        if appraisal.appraised_at > now || (now - appraisal.appraised_at) as u64 > self.max_appraisal_age {
+            return Err(ErrorCode::StaleAppraisal.into());
+        }
+
+        Ok(appraisal.price)
+    }
+}
+
+// Price attestation signed by the appraiser:
+// nft mint (32) | currency mint (32) | price (u64 le) | appraised at (i64 le)
+pub struct Appraisal {
+    pub price: u64,
+    pub appraised_at: i64,
+}
+
+impl Appraisal {
+    pub const LEN: usize = 32 + 32 + 8 + 8;
+
+    // Reads the attestation from an ed25519 program instruction earlier in the transaction.
+    pub fn load(instructions_sysvar: &AccountInfo, appraiser: &Pubkey, nft_mint: &Pubkey, stablecoin_mint: &Pubkey) -> Result<Appraisal> {
+        let current_index = load_current_index_checked(instructions_sysvar)?;
+        for index in 0..current_index {
+            let ix = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+            if ix.program_id != ed25519_program::ID {
+                continue;
+            }
+
+            for message in ed25519_messages(&ix.data, appraiser) {
+                if message.len() != Appraisal::LEN || &message[..32] != nft_mint.as_ref() || &message[32..64] != stablecoin_mint.as_ref() {
+                    continue;
+                }
+                return Ok(Appraisal {
+                    price: u64::from_le_bytes(message[64..72].try_into().unwrap()),
+                    appraised_at: i64::from_le_bytes(message[72..80].try_into().unwrap()),
+                });
+            }
+        }
+
+        Err(ErrorCode::MissingAppraisal.into())
+    }
+}
+
+// Loan terms signed off-chain by the borrower:
+// market (32) | nft mint (32) | currency mint (32) | request amount (u64 le) | interest (u64 le) |
+// period (u64 le) | expires at (u64 le) | nonce (u64 le)
+pub struct SignedOrder {
+    pub request_amount: u64,
+    pub interest: u64,
+    pub period: u64,
+    pub expires_at: u64,
+    pub nonce: u64,
+}
+
+impl SignedOrder {
+    pub const LEN: usize = 32 + 32 + 32 + 8 * 5;
+
+    // Reads the terms from an ed25519 program instruction earlier in the transaction.
+    pub fn load(instructions_sysvar: &AccountInfo, borrower: &Pubkey, market: &Pubkey, nft_mint: &Pubkey, stablecoin_mint: &Pubkey) -> Result<SignedOrder> {
+        let current_index = load_current_index_checked(instructions_sysvar)?;
+        for index in 0..current_index {
+            let ix = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+            if ix.program_id != ed25519_program::ID {
+                continue;
+            }
+
+            for message in ed25519_messages(&ix.data, borrower) {
+                if message.len() != SignedOrder::LEN
+                    || &message[..32] != market.as_ref()
+                    || &message[32..64] != nft_mint.as_ref()
+                    || &message[64..96] != stablecoin_mint.as_ref() {
+                    continue;
+                }
+                let read = |at: usize| u64::from_le_bytes(message[at..at + 8].try_into().unwrap());
+                return Ok(SignedOrder {
+                    request_amount: read(96),
+                    interest: read(104),
+                    period: read(112),
+                    expires_at: read(120),
+                    nonce: read(128),
+                });
+            }
+        }
+
+        Err(ErrorCode::MissingSignedOrder.into())
+    }
+}
+
+// Messages of an ed25519 program instruction that were signed by `signer`. Only signatures whose
+// data lives inside the instruction itself are considered.
+pub fn ed25519_messages<'a>(data: &'a [u8], signer: &Pubkey) -> Vec<&'a [u8]> {
+    const HEADER_LEN: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+
+    let mut messages = Vec::new();
+    let count = match data.first() {
+        Some(count) => *count as usize,
+        None => return messages,
+    };
+    for i in 0..count {
+        let start = HEADER_LEN + i * OFFSETS_LEN;
+        let offsets = match data.get(start..start + OFFSETS_LEN) {
+            Some(offsets) => offsets,
+            None => break,
+        };
+        let read = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+        if read(2) != u16::MAX || read(6) != u16::MAX || read(12) != u16::MAX {
+            continue;
+        }
+
+        let public_key_offset = read(4) as usize;
+        let message_offset = read(8) as usize;
+        let message_size = read(10) as usize;
+        if data.get(public_key_offset..public_key_offset + 32) != Some(signer.as_ref()) {
+            continue;
+        }
+        if let Some(message) = data.get(message_offset..message_offset + message_size) {
+            messages.push(message);
+        }
+    }
+
+    messages
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct Currency {
+    // market the currency is allowlisted on
+    pub market: Pubkey,
     // Mint of the token
     pub stablecoin_mint: Pubkey,
     // Vault holding the stablecoins -- mostly for holding the collateral stablecoins
     pub stablecoin_vault: Pubkey,
-    // latest order id
-    pub order_id: u64,
-    // total additional collateral
+    // total additional collateral held in this currency
     pub total_additional_collateral: u64,
 
-    // nonce 
-    pub nonce: u8
+    // nonce
+    pub nonce: u8,
+    // nonce of the lamport escrow, only set for native SOL
+    pub vault_nonce: u8
 }
 
 #[account]
-#[derive(Default)]
+#[derive(Default, InitSpace)]
 pub struct Order {
     // person requesting the loan
     pub borrower: Pubkey,
     /// vault to send the loan 
     pub stablecoin_vault: Pubkey,
+    // mint of the loan currency
+    pub stablecoin_mint: Pubkey,
     // mint of the nft
     pub nft_mint: Pubkey,
     /// collateral vault holding the nft
@@ -703,6 +9249,417 @@ pub struct Order {
 
     // status of the order
     pub order_status: bool,
+    // collateral is a programmable nft moved through Token Metadata
+    pub is_programmable: bool,
+    // collateral stays frozen in the borrower's wallet instead of the vault
+    pub is_escrowless: bool,
+    // only lenders on the order's allowlist may fund it
+    pub is_private: bool,
+    // mint of the promissory note held by the current lender
+    pub note_mint: Pubkey,
+    // deadline to answer an outstanding margin call, 0 when there is none
+    pub margin_call_deadline: u64,
+    // appraised price the margin call was issued at
+    pub margin_call_price: u64,
+    // unix timestamp after which the order can no longer be funded, 0 for never
+    pub expires_at: u64,
+    // funded by several lenders through the syndicate vault
+    pub is_syndicated: bool,
+    // amount contributed so far to a syndicated order
+    pub funded_amount: u64,
+    // repayment or auction proceeds shared by the syndicate
+    pub syndicate_proceeds: u64,
+    // end of the collateral auction of a defaulted syndicated loan, 0 before the first bid
+    pub auction_end: u64,
+    pub highest_bid: u64,
+    pub highest_bidder: Pubkey,
+    // lending pool that funded the loan, default for peer-to-peer loans
+    pub pool: Pubkey,
+    // price the pool asks for defaulted collateral, 0 for the debt
+    pub sale_price: u64,
+    // annual rate of a pool or open-term loan in basis points
+    pub interest_rate_bps: u64,
+    // open-term loan accruing interest per second until repaid, period is the notice window
+    pub is_perpetual: bool,
+    // time the lender called the open-term loan, 0 when not called
+    pub called_at: u64,
+
+    // nonce
+    pub nonce: u8
+}
+
+impl Order {
+    // Collateral value over debt in basis points, open-term debt includes the interest accrued so far.
+    pub fn health_bps(&self, price: u64, now: u64) -> u64 {
+        let collateral = (price as u128) + (self.additional_collateral as u128);
+        let debt = (self.request_amount as u128) + (self.interest_due(now) as u128);
+        (collateral * (BPS_DENOMINATOR as u128) / debt).min(u64::MAX as u128) as u64
+    }
+
+    // The unfunded order passed its expiry.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at != 0 && self.expires_at <= now
+    }
+
+    // Repayment deadline, open-term loans have none until called.
+    pub fn due_at(&self) -> u64 {
+        if !self.is_perpetual {
+            return self.loan_start_time.checked_add(self.period).unwrap();
+        }
+        if self.called_at == 0 {
+            return u64::MAX;
+        }
+        self.called_at.checked_add(self.period).unwrap()
+    }
+
+    // Interest owed when repaying at now.
+    pub fn interest_due(&self, now: u64) -> u64 {
+        if !self.is_perpetual {
+            return self.interest;
+        }
+        RateModel::interest(self.request_amount, self.interest_rate_bps, now.saturating_sub(self.loan_start_time))
+    }
+
+    // Rate offered to refinancing lenders, ramping from the loan's rate up to the cap over the notice window.
+    pub fn refinance_rate_bps(&self, now: u64, max_rate_bps: u64) -> u64 {
+        let max_rate_bps = max_rate_bps.max(self.interest_rate_bps);
+        let elapsed = now.saturating_sub(self.called_at);
+        if elapsed >= self.period {
+            return max_rate_bps;
+        }
+        let ramp = ((max_rate_bps - self.interest_rate_bps) as u128) * (elapsed as u128) / (self.period as u128);
+        self.interest_rate_bps + ramp as u64
+    }
+
+    // The loan expired, or a margin call went unanswered past its deadline.
+    pub fn is_liquidatable(&self, now: u64) -> bool {
+        if self.margin_call_deadline != 0 && self.margin_call_deadline < now {
+            return true;
+        }
+        self.due_at() <= now
+    }
+
+    // Clears the margin call once the top ups bring the loan back above the threshold.
+    pub fn answer_margin_call(&mut self, margin_call_threshold_bps: u16, now: u64) {
+        if self.margin_call_deadline != 0 && self.health_bps(self.margin_call_price, now) >= margin_call_threshold_bps as u64 {
+            self.margin_call_deadline = 0;
+            self.margin_call_price = 0;
+        }
+    }
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct LendingPool {
+    // market the pool lends on
+    pub market: Pubkey,
+    // verified collection accepted as collateral
+    pub collection: Pubkey,
+    // mint of the loan currency
+    pub stablecoin_mint: Pubkey,
+    // vault holding the idle liquidity
+    pub pool_vault: Pubkey,
+    // mint of the LP shares
+    pub lp_mint: Pubkey,
+    // maximum loan-to-value against the appraisal in basis points
+    pub max_ltv_bps: u16,
+    // annual interest rate curve of the pool's loans
+    pub rate_model: RateModel,
+    // the loan period
+    pub period: u64,
+    // principal of the outstanding loans
+    pub total_borrowed: u64,
+    // LP shares waiting in the withdrawal queue
+    pub queued_shares: u64,
+    // mint of the first-loss LP shares, default when the pool is not tranched
+    pub junior_lp_mint: Pubkey,
+    // part of the interest earned by the junior tranche in basis points
+    pub junior_interest_bps: u16,
+    // book value backing the senior LP shares
+    pub senior_assets: u64,
+    // book value backing the junior LP shares
+    pub junior_assets: u64,
+
+    // nonce
+    pub nonce: u8
+}
+
+// Kinked utilization curve: the rate climbs by slope1 up to the kink and by slope2 beyond it.
+// All values are annual rates or utilizations in basis points.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct RateModel {
+    pub base_rate_bps: u16,
+    pub slope1_bps: u16,
+    pub kink_bps: u16,
+    pub slope2_bps: u16,
+}
+
+impl RateModel {
+    pub fn validate(&self) -> Result<()> {
+        if self.kink_bps == 0 || self.kink_bps as u64 >= BPS_DENOMINATOR {
+            return Err(ErrorCode::InvalidRateModel.into());
+        }
+        Ok(())
+    }
+
+    // Annual rate in basis points at the given utilization of the pool.
+    pub fn rate_bps(&self, borrowed: u64, total_assets: u64) -> u64 {
+        let utilization = if total_assets == 0 {
+            0
+        } else {
+            ((borrowed as u128) * (BPS_DENOMINATOR as u128) / (total_assets as u128)).min(BPS_DENOMINATOR as u128) as u64
+        };
+        let kink = self.kink_bps as u64;
+
+        let mut rate = self.base_rate_bps as u64 + (self.slope1_bps as u64) * utilization.min(kink) / kink;
+        if utilization > kink {
+            rate += (self.slope2_bps as u64) * (utilization - kink) / (BPS_DENOMINATOR - kink);
+        }
+        rate
+    }
+
+    // Interest owed on the principal at an annual rate over the period.
+    pub fn interest(principal: u64, rate_bps: u64, period: u64) -> u64 {
+        let interest = (principal as u128).saturating_mul(rate_bps as u128).saturating_mul(period as u128)
+            / ((BPS_DENOMINATOR as u128) * (SECONDS_PER_YEAR as u128));
+        interest.min(u64::MAX as u128) as u64
+    }
+}
+
+impl LendingPool {
+    // Idle liquidity plus outstanding principal.
+    pub fn total_assets(&self, idle: u64) -> u64 {
+        idle.checked_add(self.total_borrowed).unwrap()
+    }
+
+    pub fn lp_mint_of(&self, junior: bool) -> Pubkey {
+        if junior { self.junior_lp_mint } else { self.lp_mint }
+    }
+
+    pub fn tranche_assets(&self, junior: bool) -> u64 {
+        if junior { self.junior_assets } else { self.senior_assets }
+    }
+
+    pub fn tranche_assets_mut(&mut self, junior: bool) -> &mut u64 {
+        if junior { &mut self.junior_assets } else { &mut self.senior_assets }
+    }
+
+    // LP shares worth the assets in a tranche.
+    pub fn shares_for(assets: u64, tranche_assets: u64, lp_supply: u64) -> u64 {
+        let shares = (assets as u128) * (lp_supply as u128 + VIRTUAL_LIQUIDITY as u128)
+            / (tranche_assets as u128 + VIRTUAL_LIQUIDITY as u128);
+        shares.min(u64::MAX as u128) as u64
+    }
+
+    // Assets redeemed by LP shares of a tranche, never more than the tranche holds.
+    pub fn assets_for(shares: u64, tranche_assets: u64, lp_supply: u64) -> u64 {
+        let assets = (shares as u128) * (tranche_assets as u128 + VIRTUAL_LIQUIDITY as u128)
+            / (lp_supply as u128 + VIRTUAL_LIQUIDITY as u128);
+        assets.min(tranche_assets as u128) as u64
+    }
+
+    // Books a closed loan: gains are split by the junior interest share, losses hit the
+    // junior tranche first.
+    pub fn settle(&mut self, principal: u64, proceeds: u64) {
+        if proceeds >= principal {
+            let gain = proceeds - principal;
+            let junior_gain = if self.junior_lp_mint == Pubkey::default() {
+                0
+            } else {
+                ((gain as u128) * (self.junior_interest_bps as u128) / (BPS_DENOMINATOR as u128)) as u64
+            };
+            self.junior_assets = self.junior_assets.checked_add(junior_gain).unwrap();
+            self.senior_assets = self.senior_assets.checked_add(gain - junior_gain).unwrap();
+        } else {
+            let loss = principal - proceeds;
+            let junior_loss = loss.min(self.junior_assets);
+            self.junior_assets -= junior_loss;
+            self.senior_assets = self.senior_assets.saturating_sub(loss - junior_loss);
+        }
+    }
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct WithdrawalQueue {
+    // pool the queue exits from
+    pub pool: Pubkey,
+    // pending withdrawals, oldest first
+    #[max_len(32)]
+    pub entries: Vec<QueuedWithdrawal>,
+
+    // nonce
+    pub nonce: u8
+}
+
+impl WithdrawalQueue {
+    pub const MAX_ENTRIES: usize = 32;
+    // smallest withdrawal that may be queued, keeps dust out of the queue
+    pub const MIN_SHARES: u64 = 1_000;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct QueuedWithdrawal {
+    pub lender: Pubkey,
+    // stablecoin account receiving the payout
+    pub payout_vault: Pubkey,
+    // LP shares locked in the queue
+    pub shares: u64,
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct Strategy {
+    // market the strategy lends on
+    pub market: Pubkey,
+    // lender owning the strategy
+    pub owner: Pubkey,
+    // mint of the loan currency
+    pub stablecoin_mint: Pubkey,
+    // escrow funding loans and receiving repayments
+    pub strategy_vault: Pubkey,
+    // verified collections the strategy lends against, empty for any
+    #[max_len(8)]
+    pub collections: Vec<Pubkey>,
+    // largest loan the strategy funds
+    pub max_amount: u64,
+    // minimum interest over the request amount in basis points
+    pub min_interest_bps: u64,
+    // longest loan period the strategy funds
+    pub max_period: u64,
+
+    // nonce
+    pub nonce: u8
+}
+
+impl Strategy {
+    pub const MAX_COLLECTIONS: usize = 8;
+
+    // A note held by a strategy is paid into its vault, not any other account of the strategy.
+    pub fn check_payout_vault(holder: &AccountInfo, vault: &Pubkey) -> Result<()> {
+        if *holder.owner != crate::ID {
+            return Ok(());
+        }
+        let strategy = Strategy::try_deserialize(&mut &holder.try_borrow_data()?[..])?;
+        if strategy.strategy_vault != *vault {
+            return Err(ErrorCode::StrategyMismatch.into());
+        }
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct LoanShare {
+    // syndicated order
+    pub order: Pubkey,
+    // contributing lender
+    pub lender: Pubkey,
+    // principal contributed
+    pub amount: u64,
+    // tokens held in the syndicate vault for the contribution
+    pub escrowed: u64,
+    // voted on who takes the defaulted collateral
+    pub voted: bool,
+
+    // nonce
+    pub nonce: u8
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct ClaimVote {
+    // defaulted syndicated order
+    pub order: Pubkey,
+    // account the lenders hand the collateral to
+    pub claimant: Pubkey,
+    // principal of the shares voting for the claimant
+    pub votes: u64,
+
+    // nonce
+    pub nonce: u8
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct OrderNonces {
+    // borrower whose signed orders the page tracks
+    pub borrower: Pubkey,
+    // page index, covering nonces page * NONCES_PER_PAGE onwards
+    pub page: u64,
+    // bit set for every filled nonce
+    pub bitmap: [u8; 256],
+
+    // nonce
+    pub nonce: u8
+}
+
+impl OrderNonces {
+    pub const NONCES_PER_PAGE: u64 = 256 * 8;
+
+    // Marks the nonce as filled, failing if it already was.
+    pub fn use_nonce(&mut self, nonce: u64) -> Result<()> {
+        let bit = (nonce % OrderNonces::NONCES_PER_PAGE) as usize;
+        let mask = 1u8 << (bit % 8);
+        if self.bitmap[bit / 8] & mask != 0 {
+            return Err(ErrorCode::OrderNonceUsed.into());
+        }
+        self.bitmap[bit / 8] |= mask;
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct Allowlist {
+    // order the allowlist belongs to
+    pub order: Pubkey,
+    // lenders allowed to fund the order
+    #[max_len(16)]
+    pub lenders: Vec<Pubkey>,
+
+    // nonce
+    pub nonce: u8
+}
+
+impl Allowlist {
+    pub const MAX_LENDERS: usize = 16;
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct CounterOffer {
+    // order the offer is made on
+    pub order: Pubkey,
+    // lender making the offer
+    pub lender: Pubkey,
+    // proposed terms
+    pub request_amount: u64,
+    pub interest: u64,
+    pub period: u64,
+    // amount held in the offer vault
+    pub escrowed: u64,
+
+    // nonce
+    pub nonce: u8
+}
+
+#[event]
+pub struct CollateralSurrendered {
+    pub order: Pubkey,
+    pub borrower: Pubkey,
+    pub lender: Pubkey,
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct Listing {
+    // funded order whose position is for sale
+    pub order: Pubkey,
+    // lender selling the position
+    pub seller: Pubkey,
+    // asking price in the loan currency
+    pub price: u64,
 
     // nonce
     pub nonce: u8
@@ -722,4 +9679,169 @@ pub enum ErrorCode {
     RepaymentPeriodNotExceeded,
     #[msg("Already liquidated")]
     AlreadyLiquidated,
-}
\ No newline at end of file
+    #[msg("Received amount does not match the transferred amount")]
+    ReceivedAmountMismatch,
+    #[msg("Metadata does not belong to the nft")]
+    InvalidNftMetadata,
+    #[msg("Programmable nft accounts are missing")]
+    MissingProgrammableNftAccounts,
+    #[msg("Loan-to-value must not exceed 100%")]
+    InvalidLoanToValue,
+    #[msg("No appraisal signed by the market appraiser")]
+    MissingAppraisal,
+    #[msg("Appraisal is too old")]
+    StaleAppraisal,
+    #[msg("Loan exceeds the maximum loan-to-value")]
+    LoanToValueExceeded,
+    #[msg("Margin calls are not enabled on this market")]
+    MarginCallsDisabled,
+    #[msg("A margin call is already outstanding")]
+    MarginCallActive,
+    #[msg("Loan health is above the margin call threshold")]
+    LoanHealthy,
+    #[msg("New period must be longer than the current one")]
+    PeriodNotExtended,
+    #[msg("Order has expired")]
+    OrderExpired,
+    #[msg("Order has not expired")]
+    OrderNotExpired,
+    #[msg("Order terms differ from the expected ones")]
+    OrderTermsChanged,
+    #[msg("Lender is not allowed to fund this order")]
+    LenderNotAllowed,
+    #[msg("Too many lenders on the allowlist")]
+    TooManyLenders,
+    #[msg("No order signed by the borrower")]
+    MissingSignedOrder,
+    #[msg("Order nonce does not belong to the nonce page")]
+    InvalidOrderNonce,
+    #[msg("Order nonce was already used")]
+    OrderNonceUsed,
+    #[msg("Contribution exceeds the unfunded amount")]
+    ContributionTooLarge,
+    #[msg("Loan has not been repaid or liquidated")]
+    LoanNotSettled,
+    #[msg("Auction has ended")]
+    AuctionEnded,
+    #[msg("Auction has not ended")]
+    AuctionNotEnded,
+    #[msg("Bid must exceed the highest bid")]
+    BidTooLow,
+    #[msg("Vault of the outbid bidder is missing")]
+    MissingBidderVault,
+    #[msg("Order still holds contributions")]
+    OrderPartiallyFunded,
+    #[msg("Not enough idle liquidity in the pool")]
+    InsufficientLiquidity,
+    #[msg("Nft is not part of the pool's collection")]
+    CollectionMismatch,
+    #[msg("Rate model kink must be between 0 and 100%")]
+    InvalidRateModel,
+    #[msg("Withdrawal queue is full")]
+    WithdrawalQueueFull,
+    #[msg("Withdrawal queue is empty")]
+    WithdrawalQueueEmpty,
+    #[msg("Junior interest share must not exceed 100%")]
+    InvalidInterestShare,
+    #[msg("Too many collections on the strategy")]
+    TooManyCollections,
+    #[msg("Order does not match the strategy")]
+    StrategyMismatch,
+    #[msg("Open-term loans are not supported for this order")]
+    OpenTermNotSupported,
+    #[msg("Loan is not open-term")]
+    NotOpenTerm,
+    #[msg("Loan has already been called")]
+    LoanAlreadyCalled,
+    #[msg("Loan has not been called")]
+    LoanNotCalled,
+    #[msg("Programmable nfts are not supported for SOL orders")]
+    ProgrammableNotSupported,
+    #[msg("Invalid margin call threshold or period")]
+    InvalidMarginCallConfig,
+    #[msg("Loan is listed for sale, delist the note first")]
+    LoanListed,
+    #[msg("Listing price is above the buyer's maximum")]
+    PriceAboveMax,
+    #[msg("Bid is below the reserve")]
+    BidBelowReserve,
+    #[msg("Share has already voted")]
+    AlreadyVoted,
+    #[msg("Claimant is not backed by a majority of the syndicate")]
+    ClaimNotApproved,
+    #[msg("Collateral is being auctioned")]
+    AuctionStarted,
+    #[msg("Price is below the appraised value")]
+    PriceBelowAppraisal,
+    #[msg("Withdrawal is below the queue minimum")]
+    WithdrawalTooSmall,
+    #[msg("Tranche was wiped out by losses")]
+    TrancheWipedOut,
+    #[msg("Junior tranche assets cover outstanding loans")]
+    JuniorTrancheLocked,
+    #[msg("Collateral is no longer locked")]
+    CollateralReleased,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_model() -> RateModel {
+        RateModel { base_rate_bps: 200, slope1_bps: 800, kink_bps: 8_000, slope2_bps: 5_000 }
+    }
+
+    #[test]
+    fn rate_at_zero_utilization() {
+        assert_eq!(rate_model().rate_bps(0, 1_000), 200);
+        assert_eq!(rate_model().rate_bps(0, 0), 200);
+    }
+
+    #[test]
+    fn rate_at_kink() {
+        assert_eq!(rate_model().rate_bps(800, 1_000), 1_000);
+    }
+
+    #[test]
+    fn rate_below_kink() {
+        assert_eq!(rate_model().rate_bps(400, 1_000), 600);
+    }
+
+    #[test]
+    fn rate_above_kink() {
+        assert_eq!(rate_model().rate_bps(900, 1_000), 3_500);
+    }
+
+    #[test]
+    fn rate_at_full_utilization() {
+        assert_eq!(rate_model().rate_bps(1_000, 1_000), 6_000);
+        // Utilization is capped at 100%.
+        assert_eq!(rate_model().rate_bps(2_000, 1_000), 6_000);
+    }
+
+    #[test]
+    fn validate_rejects_kink_out_of_range() {
+        assert!(rate_model().validate().is_ok());
+        assert!(RateModel { kink_bps: 0, ..rate_model() }.validate().is_err());
+        assert!(RateModel { kink_bps: 10_000, ..rate_model() }.validate().is_err());
+    }
+
+    #[test]
+    fn interest_over_a_year() {
+        assert_eq!(RateModel::interest(1_000_000, 1_000, SECONDS_PER_YEAR), 100_000);
+        assert_eq!(RateModel::interest(1_000_000, 0, SECONDS_PER_YEAR), 0);
+    }
+
+    #[test]
+    fn interest_rounds_down() {
+        assert_eq!(RateModel::interest(10_000, 10_000, SECONDS_PER_YEAR - 1), 9_999);
+        assert_eq!(RateModel::interest(1_000_000, 1_000, 1), 0);
+    }
+
+    #[test]
+    fn interest_saturates_at_u64_max() {
+        assert_eq!(RateModel::interest(u64::MAX, 10_000, SECONDS_PER_YEAR), u64::MAX);
+        assert_eq!(RateModel::interest(u64::MAX, 20_000, SECONDS_PER_YEAR), u64::MAX);
+        assert_eq!(RateModel::interest(u64::MAX, u64::MAX, u64::MAX), u64::MAX);
+    }
+}
@@ -0,0 +1,278 @@
+#![allow(dead_code)]
+
+use anchor_lang::{AnchorSerialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use anchor_spl::metadata::mpl_token_metadata::{
+    self,
+    accounts::{MasterEdition, Metadata},
+    types::{Collection, Key},
+};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use nft_collaterized_loans::{accounts, instruction, NFTCollaterizedLoans};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    clock::Clock,
+    ed25519_program,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+fn entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    // Anchor ties the account infos to one lifetime, the test runtime hands them out per call.
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    nft_collaterized_loans::entry(program_id, accounts, data)
+}
+
+// Token Metadata isn't bundled with the test validator, the notes' metadata calls are no-ops.
+fn token_metadata_stub(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+pub async fn start() -> ProgramTestContext {
+    let mut program_test = ProgramTest::new("nft_collaterized_loans", nft_collaterized_loans::ID, processor!(entry));
+    program_test.add_program("mpl_token_metadata", mpl_token_metadata::ID, processor!(token_metadata_stub));
+    program_test.start_with_context().await
+}
+
+pub async fn process(ctx: &mut ProgramTestContext, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), BanksClientError> {
+    let mut all_signers = vec![&ctx.payer];
+    all_signers.extend_from_slice(signers);
+    let blockhash = ctx.banks_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(instructions, Some(&ctx.payer.pubkey()), &all_signers, blockhash);
+    ctx.banks_client.process_transaction(transaction).await
+}
+
+pub fn ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: nft_collaterized_loans::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+// Asserts the transaction failed with the program's error.
+pub fn assert_error(result: Result<(), BanksClientError>, error: nft_collaterized_loans::ErrorCode) {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => assert_eq!(code, u32::from(error)),
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+pub async fn funded_keypair(ctx: &mut ProgramTestContext, lamports: u64) -> Keypair {
+    let keypair = Keypair::new();
+    let transfer = system_instruction::transfer(&ctx.payer.pubkey(), &keypair.pubkey(), lamports);
+    process(ctx, &[transfer], &[]).await.unwrap();
+    keypair
+}
+
+pub async fn lamports(ctx: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    ctx.banks_client.get_balance(*address).await.unwrap()
+}
+
+pub async fn token_balance(ctx: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    let account = ctx.banks_client.get_account(*address).await.unwrap().unwrap();
+    StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).unwrap().base.amount
+}
+
+pub async fn warp_forward(ctx: &mut ProgramTestContext, seconds: i64) {
+    let mut clock = ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += seconds;
+    ctx.set_sysvar(&clock);
+}
+
+// Mint with the payer as mint and freeze authority, optionally charging a transfer fee under Token-2022.
+pub async fn create_mint(ctx: &mut ProgramTestContext, token_program: &Pubkey, decimals: u8, transfer_fee_bps: Option<u16>) -> Pubkey {
+    let mint = Keypair::new();
+    let authority = ctx.payer.pubkey();
+    let extensions: &[ExtensionType] = if transfer_fee_bps.is_some() { &[ExtensionType::TransferFeeConfig] } else { &[] };
+    let space = if *token_program == spl_token::ID {
+        spl_token::state::Mint::LEN
+    } else {
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(extensions).unwrap()
+    };
+    let rent = ctx.banks_client.get_rent().await.unwrap().minimum_balance(space);
+
+    let mut instructions = vec![system_instruction::create_account(&authority, &mint.pubkey(), rent, space as u64, token_program)];
+    if let Some(transfer_fee_bps) = transfer_fee_bps {
+        instructions.push(transfer_fee::instruction::initialize_transfer_fee_config(
+            token_program,
+            &mint.pubkey(),
+            Some(&authority),
+            Some(&authority),
+            transfer_fee_bps,
+            u64::MAX,
+        ).unwrap());
+    }
+    instructions.push(spl_token_2022::instruction::initialize_mint2(token_program, &mint.pubkey(), &authority, Some(&authority), decimals).unwrap());
+    process(ctx, &instructions, &[&mint]).await.unwrap();
+    mint.pubkey()
+}
+
+pub async fn create_token_account(ctx: &mut ProgramTestContext, owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    let address = get_associated_token_address_with_program_id(owner, mint, token_program);
+    let create = Instruction {
+        program_id: associated_token::ID,
+        accounts: vec![
+            AccountMeta::new(ctx.payer.pubkey(), true),
+            AccountMeta::new(address, false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+        data: vec![],
+    };
+    process(ctx, &[create], &[]).await.unwrap();
+    address
+}
+
+pub async fn mint_to(ctx: &mut ProgramTestContext, mint: &Pubkey, account: &Pubkey, amount: u64, token_program: &Pubkey) {
+    let authority = ctx.payer.pubkey();
+    let mint_to = spl_token_2022::instruction::mint_to(token_program, mint, account, &authority, &[], amount).unwrap();
+    process(ctx, &[mint_to], &[]).await.unwrap();
+}
+
+// Nft held in the owner's associated account.
+pub async fn create_nft(ctx: &mut ProgramTestContext, owner: &Pubkey, token_program: &Pubkey) -> (Pubkey, Pubkey) {
+    let mint = create_mint(ctx, token_program, 0, None).await;
+    let account = create_token_account(ctx, owner, &mint, token_program).await;
+    mint_to(ctx, &mint, &account, 1, token_program).await;
+    (mint, account)
+}
+
+// Fee the mint withholds from a transfer of `amount`.
+pub async fn transfer_fee(ctx: &mut ProgramTestContext, mint: &Pubkey, amount: u64) -> u64 {
+    let account = ctx.banks_client.get_account(*mint).await.unwrap().unwrap();
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data).unwrap();
+    let epoch = ctx.banks_client.get_sysvar::<Clock>().await.unwrap().epoch;
+    mint_state.get_extension::<transfer_fee::TransferFeeConfig>().unwrap().calculate_epoch_fee(epoch, amount).unwrap()
+}
+
+pub struct Market {
+    pub key: Pubkey,
+    pub signer: Pubkey,
+    pub authority: Keypair,
+}
+
+pub async fn create_market(ctx: &mut ProgramTestContext) -> Market {
+    let market = Keypair::new();
+    let authority = funded_keypair(ctx, 1_000_000_000).await;
+    let (signer, nonce) = Pubkey::find_program_address(&[market.pubkey().as_ref()], &nft_collaterized_loans::ID);
+
+    let space = 8 + <NFTCollaterizedLoans as anchor_lang::Space>::INIT_SPACE;
+    let rent = ctx.banks_client.get_rent().await.unwrap().minimum_balance(space);
+    let create = system_instruction::create_account(&ctx.payer.pubkey(), &market.pubkey(), rent, space as u64, &nft_collaterized_loans::ID);
+    let initialize = ix(
+        accounts::Initialize {
+            nft_collaterized_loans: market.pubkey(),
+            authority: authority.pubkey(),
+            signer,
+        },
+        instruction::Initialize { nonce },
+    );
+    process(ctx, &[create, initialize], &[&market, &authority]).await.unwrap();
+
+    Market { key: market.pubkey(), signer, authority }
+}
+
+pub fn currency_address(market: &Pubkey, stablecoin_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"currency", stablecoin_mint.as_ref(), market.as_ref()], &nft_collaterized_loans::ID)
+}
+
+pub fn order_address(market: &Pubkey, order_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[order_id.to_string().as_ref(), market.as_ref()], &nft_collaterized_loans::ID)
+}
+
+pub fn note_mint_address(order: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"note", order.as_ref()], &nft_collaterized_loans::ID).0
+}
+
+pub fn note_account_address(owner: &Pubkey, note_mint: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, note_mint, &spl_token::ID)
+}
+
+pub fn note_metadata(note_mint: &Pubkey) -> accounts::NoteMetadata {
+    accounts::NoteMetadata {
+        metadata: Metadata::find_pda(note_mint).0,
+        master_edition: MasterEdition::find_pda(note_mint).0,
+        token_metadata_program: mpl_token_metadata::ID,
+    }
+}
+
+// Collateral that isn't a programmable nft.
+pub fn no_pnft() -> accounts::ProgrammableNft {
+    accounts::ProgrammableNft {
+        nft_metadata: None,
+        nft_edition: None,
+        owner_token_record: None,
+        destination_token_record: None,
+        authorization_rules: None,
+        authorization_rules_program: None,
+        token_metadata_program: None,
+        sysvar_instructions: None,
+        associated_token_program: None,
+    }
+}
+
+// Metadata of an nft verified as part of the collection, written straight to the account.
+pub async fn set_collection_metadata(ctx: &mut ProgramTestContext, nft_mint: &Pubkey, collection: &Pubkey) -> Pubkey {
+    let address = Metadata::find_pda(nft_mint).0;
+    let metadata = Metadata {
+        key: Key::MetadataV1,
+        update_authority: ctx.payer.pubkey(),
+        mint: *nft_mint,
+        name: String::new(),
+        symbol: String::new(),
+        uri: String::new(),
+        seller_fee_basis_points: 0,
+        creators: None,
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: None,
+        collection: Some(Collection { verified: true, key: *collection }),
+        uses: None,
+        collection_details: None,
+        programmable_config: None,
+    };
+    let data = metadata.try_to_vec().unwrap();
+    let rent = ctx.banks_client.get_rent().await.unwrap().minimum_balance(data.len());
+    let account = Account { lamports: rent, data, owner: mpl_token_metadata::ID, executable: false, rent_epoch: 0 };
+    ctx.set_account(&address, &account.into());
+    address
+}
+
+// Ed25519 program instruction carrying the appraiser's signed price for the nft.
+pub fn appraisal_instruction(appraiser: &Keypair, nft_mint: &Pubkey, stablecoin_mint: &Pubkey, price: u64, appraised_at: i64) -> Instruction {
+    const PUBLIC_KEY_OFFSET: u16 = 16;
+    const SIGNATURE_OFFSET: u16 = PUBLIC_KEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+
+    let mut message = Vec::new();
+    message.extend_from_slice(nft_mint.as_ref());
+    message.extend_from_slice(stablecoin_mint.as_ref());
+    message.extend_from_slice(&price.to_le_bytes());
+    message.extend_from_slice(&appraised_at.to_le_bytes());
+    let signature = appraiser.sign_message(&message);
+
+    let mut data = vec![1, 0];
+    for offset in [SIGNATURE_OFFSET, u16::MAX, PUBLIC_KEY_OFFSET, u16::MAX, MESSAGE_OFFSET, message.len() as u16, u16::MAX] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(appraiser.pubkey().as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(&message);
+
+    Instruction { program_id: ed25519_program::ID, accounts: vec![], data }
+}
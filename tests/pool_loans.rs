@@ -0,0 +1,209 @@
+mod common;
+
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use anchor_spl::token::spl_token;
+use common::*;
+use nft_collaterized_loans::{accounts, instruction, ErrorCode, RateModel};
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_sdk::{
+    clock::Clock,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program, sysvar,
+};
+
+const SENIOR_DEPOSIT: u64 = 1_000_000;
+const JUNIOR_DEPOSIT: u64 = 3_000_000;
+const REQUEST_AMOUNT: u64 = 2_000_000;
+const PRICE: u64 = 4_000_000;
+const PERIOD: u64 = 86_400;
+
+struct Pool {
+    market: Market,
+    pool: Pubkey,
+    stablecoin_mint: Pubkey,
+    pool_vault: Pubkey,
+    lp_mint: Pubkey,
+    junior_lp_mint: Pubkey,
+    collection: Pubkey,
+    appraiser: Keypair,
+}
+
+async fn create_pool(ctx: &mut ProgramTestContext) -> Pool {
+    let market = create_market(ctx).await;
+    let appraiser = Keypair::new();
+    let collection = Pubkey::new_unique();
+    let stablecoin_mint = create_mint(ctx, &spl_token::ID, 6, None).await;
+    let stablecoin_vault = create_token_account(ctx, &market.signer, &stablecoin_mint, &spl_token::ID).await;
+    let (currency, nonce) = currency_address(&market.key, &stablecoin_mint);
+
+    let add_currency = ix(
+        accounts::AddCurrency {
+            nft_collaterized_loans: market.key,
+            currency,
+            stablecoin_mint,
+            stablecoin_vault,
+            authority: market.authority.pubkey(),
+            signer: market.signer,
+            system_program: system_program::ID,
+        },
+        instruction::AddCurrency { nonce },
+    );
+    let set_appraisal_config = ix(
+        accounts::SetAppraisalConfig { nft_collaterized_loans: market.key, authority: market.authority.pubkey() },
+        instruction::SetAppraisalConfig { appraiser: appraiser.pubkey(), max_ltv_bps: 5_000, max_appraisal_age: 3_600 },
+    );
+    process(ctx, &[add_currency, set_appraisal_config], &[&market.authority]).await.unwrap();
+
+    let (pool, nonce) = Pubkey::find_program_address(
+        &[b"pool", market.key.as_ref(), collection.as_ref(), stablecoin_mint.as_ref()],
+        &nft_collaterized_loans::ID,
+    );
+    let pool_vault = Pubkey::find_program_address(&[b"pool_vault", pool.as_ref()], &nft_collaterized_loans::ID).0;
+    let lp_mint = Pubkey::find_program_address(&[b"lp_mint", pool.as_ref()], &nft_collaterized_loans::ID).0;
+    let junior_lp_mint = Pubkey::find_program_address(&[b"junior_lp_mint", pool.as_ref()], &nft_collaterized_loans::ID).0;
+
+    let create_pool = ix(
+        accounts::CreatePool {
+            nft_collaterized_loans: market.key,
+            currency,
+            pool,
+            stablecoin_mint,
+            pool_vault,
+            lp_mint,
+            authority: market.authority.pubkey(),
+            signer: market.signer,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+            lp_token_program: spl_token::ID,
+        },
+        instruction::CreatePool {
+            nonce,
+            collection,
+            max_ltv_bps: 5_000,
+            rate_model: RateModel { base_rate_bps: 200, slope1_bps: 400, kink_bps: 8_000, slope2_bps: 6_000 },
+            period: PERIOD,
+        },
+    );
+    let enable_junior_tranche = ix(
+        accounts::EnableJuniorTranche {
+            nft_collaterized_loans: market.key,
+            pool,
+            stablecoin_mint,
+            junior_lp_mint,
+            authority: market.authority.pubkey(),
+            signer: market.signer,
+            system_program: system_program::ID,
+            lp_token_program: spl_token::ID,
+        },
+        instruction::EnableJuniorTranche { junior_interest_bps: 2_000 },
+    );
+    process(ctx, &[create_pool, enable_junior_tranche], &[&market.authority]).await.unwrap();
+
+    Pool { market, pool, stablecoin_mint, pool_vault, lp_mint, junior_lp_mint, collection, appraiser }
+}
+
+// Deposits into the tranche and returns the lender with its stablecoin account.
+async fn deposit(ctx: &mut ProgramTestContext, pool: &Pool, amount: u64, junior: bool) -> (Keypair, Pubkey) {
+    let lender = funded_keypair(ctx, 1_000_000_000).await;
+    let lender_stablecoin_vault = create_token_account(ctx, &lender.pubkey(), &pool.stablecoin_mint, &spl_token::ID).await;
+    mint_to(ctx, &pool.stablecoin_mint, &lender_stablecoin_vault, amount, &spl_token::ID).await;
+    let lp_mint = if junior { pool.junior_lp_mint } else { pool.lp_mint };
+
+    let deposit = ix(
+        accounts::Deposit {
+            nft_collaterized_loans: pool.market.key,
+            pool: pool.pool,
+            stablecoin_mint: pool.stablecoin_mint,
+            pool_vault: pool.pool_vault,
+            lender_stablecoin_vault,
+            lp_mint,
+            lender_lp_account: get_associated_token_address_with_program_id(&lender.pubkey(), &lp_mint, &spl_token::ID),
+            lender: lender.pubkey(),
+            signer: pool.market.signer,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+            lp_token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+        },
+        instruction::Deposit { amount, junior },
+    );
+    process(ctx, &[deposit], &[&lender]).await.unwrap();
+
+    (lender, lender_stablecoin_vault)
+}
+
+async fn borrow(ctx: &mut ProgramTestContext, pool: &Pool, request_amount: u64) {
+    let borrower = funded_keypair(ctx, 1_000_000_000).await;
+    let user_stablecoin_vault = create_token_account(ctx, &borrower.pubkey(), &pool.stablecoin_mint, &spl_token::ID).await;
+    let (nft_mint, user_nft_vault) = create_nft(ctx, &borrower.pubkey(), &spl_token::ID).await;
+    let nft_vault = create_token_account(ctx, &pool.market.signer, &nft_mint, &spl_token::ID).await;
+    let nft_metadata = set_collection_metadata(ctx, &nft_mint, &pool.collection).await;
+    let (order, nonce) = order_address(&pool.market.key, 0);
+
+    let now = ctx.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp;
+    let appraisal = appraisal_instruction(&pool.appraiser, &nft_mint, &pool.stablecoin_mint, PRICE, now);
+    let borrow_from_pool = ix(
+        accounts::BorrowFromPool {
+            nft_collaterized_loans: pool.market.key,
+            pool: pool.pool,
+            stablecoin_mint: pool.stablecoin_mint,
+            pool_vault: pool.pool_vault,
+            lp_mint: pool.lp_mint,
+            user_stablecoin_vault,
+            nft_mint,
+            nft_vault,
+            user_nft_vault,
+            nft_metadata,
+            order,
+            borrower: borrower.pubkey(),
+            signer: pool.market.signer,
+            instructions_sysvar: sysvar::instructions::ID,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+            nft_token_program: spl_token::ID,
+        },
+        instruction::BorrowFromPool { nonce, request_amount },
+    );
+    process(ctx, &[appraisal, borrow_from_pool], &[&borrower]).await.unwrap();
+
+    assert_eq!(token_balance(ctx, &user_stablecoin_vault).await, request_amount);
+    assert_eq!(token_balance(ctx, &nft_vault).await, 1);
+}
+
+fn withdraw(pool: &Pool, lender: &Keypair, lender_stablecoin_vault: Pubkey, shares: u64) -> Instruction {
+    ix(
+        accounts::Withdraw {
+            nft_collaterized_loans: pool.market.key,
+            pool: pool.pool,
+            stablecoin_mint: pool.stablecoin_mint,
+            pool_vault: pool.pool_vault,
+            lender_stablecoin_vault,
+            lp_mint: pool.junior_lp_mint,
+            lender_lp_account: get_associated_token_address_with_program_id(&lender.pubkey(), &pool.junior_lp_mint, &spl_token::ID),
+            lender: lender.pubkey(),
+            signer: pool.market.signer,
+            token_program: spl_token::ID,
+            lp_token_program: spl_token::ID,
+        },
+        instruction::Withdraw { shares, junior: true },
+    )
+}
+
+#[tokio::test]
+async fn junior_lender_exits_above_the_outstanding_loans() {
+    let mut ctx = start().await;
+    let pool = create_pool(&mut ctx).await;
+    deposit(&mut ctx, &pool, SENIOR_DEPOSIT, false).await;
+    let (lender, lender_stablecoin_vault) = deposit(&mut ctx, &pool, JUNIOR_DEPOSIT, true).await;
+    borrow(&mut ctx, &pool, REQUEST_AMOUNT).await;
+
+    // Only the junior assets covering the loan stay locked.
+    let free = JUNIOR_DEPOSIT - REQUEST_AMOUNT;
+    process(&mut ctx, &[withdraw(&pool, &lender, lender_stablecoin_vault, free)], &[&lender]).await.unwrap();
+    assert_eq!(token_balance(&mut ctx, &lender_stablecoin_vault).await, free);
+
+    let result = process(&mut ctx, &[withdraw(&pool, &lender, lender_stablecoin_vault, 1)], &[&lender]).await;
+    assert_error(result, ErrorCode::JuniorTrancheLocked);
+}
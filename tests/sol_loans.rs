@@ -0,0 +1,261 @@
+mod common;
+
+use anchor_spl::associated_token;
+use anchor_spl::token::{spl_token, spl_token::native_mint};
+use common::*;
+use nft_collaterized_loans::{accounts, instruction, ErrorCode};
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+
+const REQUEST_AMOUNT: u64 = 2_000_000_000;
+const INTEREST: u64 = 100_000_000;
+const PERIOD: u64 = 86_400;
+const ADDITIONAL_COLLATERAL: u64 = 500_000_000;
+
+struct SolOrder {
+    market: Market,
+    currency: Pubkey,
+    sol_vault: Pubkey,
+    order: Pubkey,
+    borrower: Keypair,
+    nft_mint: Pubkey,
+    nft_vault: Pubkey,
+    user_nft_vault: Pubkey,
+}
+
+async fn setup(ctx: &mut ProgramTestContext) -> (Market, Pubkey, Pubkey) {
+    let market = create_market(ctx).await;
+    let (currency, nonce) = currency_address(&market.key, &native_mint::ID);
+    let (sol_vault, vault_nonce) = Pubkey::find_program_address(&[b"sol_vault", market.key.as_ref()], &nft_collaterized_loans::ID);
+
+    let add_sol_currency = ix(
+        accounts::AddSolCurrency {
+            nft_collaterized_loans: market.key,
+            currency,
+            sol_vault,
+            authority: market.authority.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::AddSolCurrency { nonce, vault_nonce },
+    );
+    process(ctx, &[add_sol_currency], &[&market.authority]).await.unwrap();
+
+    (market, currency, sol_vault)
+}
+
+async fn create_sol_order(ctx: &mut ProgramTestContext) -> SolOrder {
+    let (market, currency, sol_vault) = setup(ctx).await;
+    let borrower = funded_keypair(ctx, 10_000_000_000).await;
+    let (nft_mint, user_nft_vault) = create_nft(ctx, &borrower.pubkey(), &spl_token::ID).await;
+    let nft_vault = create_token_account(ctx, &market.signer, &nft_mint, &spl_token::ID).await;
+    let (order, nonce) = order_address(&market.key, 0);
+
+    let create_sol_order = ix(
+        accounts::CreateSolOrder {
+            nft_collaterized_loans: market.key,
+            currency,
+            sol_vault,
+            nft_mint,
+            nft_vault,
+            user_nft_vault,
+            order,
+            borrower: borrower.pubkey(),
+            signer: market.signer,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+            nft_token_program: spl_token::ID,
+        },
+        instruction::CreateSolOrder {
+            nonce,
+            request_amount: REQUEST_AMOUNT,
+            interest: INTEREST,
+            period: PERIOD,
+            additional_collateral: ADDITIONAL_COLLATERAL,
+            expires_at: 0,
+        },
+    );
+    let sol_vault_before = lamports(ctx, &sol_vault).await;
+    process(ctx, &[create_sol_order], &[&borrower]).await.unwrap();
+
+    assert_eq!(token_balance(ctx, &nft_vault).await, 1);
+    assert_eq!(lamports(ctx, &sol_vault).await - sol_vault_before, ADDITIONAL_COLLATERAL);
+
+    SolOrder { market, currency, sol_vault, order, borrower, nft_mint, nft_vault, user_nft_vault }
+}
+
+async fn give_sol_loan(ctx: &mut ProgramTestContext, sol_order: &SolOrder) -> Keypair {
+    let lender = funded_keypair(ctx, 10_000_000_000).await;
+    let note_mint = note_mint_address(&sol_order.order);
+
+    let give_sol_loan = ix(
+        accounts::GiveSolLoan {
+            nft_collaterized_loans: sol_order.market.key,
+            order: sol_order.order,
+            borrower: sol_order.borrower.pubkey(),
+            lender: lender.pubkey(),
+            note_mint,
+            lender_note_account: note_account_address(&lender.pubkey(), &note_mint),
+            note_metadata: note_metadata(&note_mint),
+            allowlist: None,
+            signer: sol_order.market.signer,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+            note_token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+        },
+        instruction::GiveSolLoan { _order_id: 0, request_amount: REQUEST_AMOUNT, interest: INTEREST, period: PERIOD },
+    );
+    let borrower_before = lamports(ctx, &sol_order.borrower.pubkey()).await;
+    process(ctx, &[give_sol_loan], &[&lender]).await.unwrap();
+
+    assert_eq!(lamports(ctx, &sol_order.borrower.pubkey()).await - borrower_before, REQUEST_AMOUNT);
+    assert_eq!(token_balance(ctx, &note_account_address(&lender.pubkey(), &note_mint)).await, 1);
+
+    lender
+}
+
+#[tokio::test]
+async fn sol_loan_is_paid_back() {
+    let mut ctx = start().await;
+    let sol_order = create_sol_order(&mut ctx).await;
+    let lender = give_sol_loan(&mut ctx, &sol_order).await;
+    let note_mint = note_mint_address(&sol_order.order);
+
+    let payback_sol = ix(
+        accounts::PaybackSol {
+            nft_collaterized_loans: sol_order.market.key,
+            currency: sol_order.currency,
+            sol_vault: sol_order.sol_vault,
+            order: sol_order.order,
+            lender: lender.pubkey(),
+            nft_mint: sol_order.nft_mint,
+            nft_vault: sol_order.nft_vault,
+            user_nft_vault: sol_order.user_nft_vault,
+            borrower: sol_order.borrower.pubkey(),
+            payer: sol_order.borrower.pubkey(),
+            signer: sol_order.market.signer,
+            note_account: note_account_address(&lender.pubkey(), &note_mint),
+            system_program: system_program::ID,
+            nft_token_program: spl_token::ID,
+        },
+        instruction::PaybackSol { _order_id: 0 },
+    );
+    let lender_before = lamports(&mut ctx, &lender.pubkey()).await;
+    let borrower_before = lamports(&mut ctx, &sol_order.borrower.pubkey()).await;
+    let sol_vault_before = lamports(&mut ctx, &sol_order.sol_vault).await;
+    process(&mut ctx, &[payback_sol], &[&sol_order.borrower]).await.unwrap();
+
+    assert_eq!(lamports(&mut ctx, &lender.pubkey()).await - lender_before, REQUEST_AMOUNT + INTEREST);
+    assert_eq!(borrower_before - lamports(&mut ctx, &sol_order.borrower.pubkey()).await, REQUEST_AMOUNT + INTEREST - ADDITIONAL_COLLATERAL);
+    assert_eq!(sol_vault_before - lamports(&mut ctx, &sol_order.sol_vault).await, ADDITIONAL_COLLATERAL);
+    assert_eq!(token_balance(&mut ctx, &sol_order.user_nft_vault).await, 1);
+    assert_eq!(token_balance(&mut ctx, &sol_order.nft_vault).await, 0);
+}
+
+#[tokio::test]
+async fn sol_order_is_cancelled() {
+    let mut ctx = start().await;
+    let sol_order = create_sol_order(&mut ctx).await;
+
+    let cancel_sol_order = ix(
+        accounts::CancelSolOrder {
+            nft_collaterized_loans: sol_order.market.key,
+            currency: sol_order.currency,
+            sol_vault: sol_order.sol_vault,
+            order: sol_order.order,
+            nft_mint: sol_order.nft_mint,
+            nft_vault: sol_order.nft_vault,
+            user_nft_vault: sol_order.user_nft_vault,
+            borrower: sol_order.borrower.pubkey(),
+            signer: sol_order.market.signer,
+            system_program: system_program::ID,
+            nft_token_program: spl_token::ID,
+        },
+        instruction::CancelSolOrder { _order_id: 0 },
+    );
+    let borrower_before = lamports(&mut ctx, &sol_order.borrower.pubkey()).await;
+    process(&mut ctx, &[cancel_sol_order], &[&sol_order.borrower]).await.unwrap();
+
+    assert_eq!(lamports(&mut ctx, &sol_order.borrower.pubkey()).await - borrower_before, ADDITIONAL_COLLATERAL);
+    assert_eq!(token_balance(&mut ctx, &sol_order.user_nft_vault).await, 1);
+}
+
+#[tokio::test]
+async fn sol_loan_is_liquidated_after_the_period() {
+    let mut ctx = start().await;
+    let sol_order = create_sol_order(&mut ctx).await;
+    let lender = give_sol_loan(&mut ctx, &sol_order).await;
+    let note_mint = note_mint_address(&sol_order.order);
+    let lender_nft_vault = create_token_account(&mut ctx, &lender.pubkey(), &sol_order.nft_mint, &spl_token::ID).await;
+
+    let liquidate_sol = ix(
+        accounts::LiquidateSol {
+            nft_collaterized_loans: sol_order.market.key,
+            currency: sol_order.currency,
+            sol_vault: sol_order.sol_vault,
+            order: sol_order.order,
+            nft_mint: sol_order.nft_mint,
+            nft_vault: sol_order.nft_vault,
+            user_nft_vault: lender_nft_vault,
+            lender: lender.pubkey(),
+            note_account: note_account_address(&lender.pubkey(), &note_mint),
+            signer: sol_order.market.signer,
+            system_program: system_program::ID,
+            nft_token_program: spl_token::ID,
+        },
+        instruction::LiquidateSol { _order_id: 0 },
+    );
+    let result = process(&mut ctx, std::slice::from_ref(&liquidate_sol), &[&lender]).await;
+    assert_error(result, ErrorCode::RepaymentPeriodNotExceeded);
+
+    warp_forward(&mut ctx, PERIOD as i64 + 1).await;
+    let lender_before = lamports(&mut ctx, &lender.pubkey()).await;
+    process(&mut ctx, &[liquidate_sol], &[&lender]).await.unwrap();
+
+    assert_eq!(lamports(&mut ctx, &lender.pubkey()).await - lender_before, ADDITIONAL_COLLATERAL);
+    assert_eq!(token_balance(&mut ctx, &lender_nft_vault).await, 1);
+}
+
+#[tokio::test]
+async fn sol_order_rejects_frozen_collateral() {
+    let mut ctx = start().await;
+    let (market, currency, sol_vault) = setup(&mut ctx).await;
+    let borrower = funded_keypair(&mut ctx, 10_000_000_000).await;
+    let (nft_mint, user_nft_vault) = create_nft(&mut ctx, &borrower.pubkey(), &spl_token::ID).await;
+    let nft_vault = create_token_account(&mut ctx, &market.signer, &nft_mint, &spl_token::ID).await;
+
+    // Programmable nfts sit frozen in the owner's wallet.
+    let freeze = spl_token::instruction::freeze_account(&spl_token::ID, &user_nft_vault, &nft_mint, &ctx.payer.pubkey(), &[]).unwrap();
+    process(&mut ctx, &[freeze], &[]).await.unwrap();
+
+    let create_sol_order = ix(
+        accounts::CreateSolOrder {
+            nft_collaterized_loans: market.key,
+            currency,
+            sol_vault,
+            nft_mint,
+            nft_vault,
+            user_nft_vault,
+            order: order_address(&market.key, 0).0,
+            borrower: borrower.pubkey(),
+            signer: market.signer,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+            nft_token_program: spl_token::ID,
+        },
+        instruction::CreateSolOrder {
+            nonce: order_address(&market.key, 0).1,
+            request_amount: REQUEST_AMOUNT,
+            interest: INTEREST,
+            period: PERIOD,
+            additional_collateral: ADDITIONAL_COLLATERAL,
+            expires_at: 0,
+        },
+    );
+    let result = process(&mut ctx, &[create_sol_order], &[&borrower]).await;
+    assert_error(result, ErrorCode::ProgrammableNotSupported);
+}
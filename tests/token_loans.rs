@@ -0,0 +1,235 @@
+mod common;
+
+use anchor_spl::associated_token;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use common::*;
+use nft_collaterized_loans::{accounts, instruction};
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+
+const REQUEST_AMOUNT: u64 = 2_000_000;
+const INTEREST: u64 = 100_000;
+const PERIOD: u64 = 86_400;
+const ADDITIONAL_COLLATERAL: u64 = 500_000;
+
+struct TokenOrder {
+    market: Market,
+    currency: Pubkey,
+    stablecoin_mint: Pubkey,
+    stablecoin_vault: Pubkey,
+    order: Pubkey,
+    borrower: Keypair,
+    borrower_stablecoin_vault: Pubkey,
+    nft_mint: Pubkey,
+    nft_vault: Pubkey,
+    user_nft_vault: Pubkey,
+    token_program: Pubkey,
+    nft_token_program: Pubkey,
+}
+
+async fn create_order(ctx: &mut ProgramTestContext, token_program: Pubkey, nft_token_program: Pubkey, transfer_fee_bps: Option<u16>) -> TokenOrder {
+    let market = create_market(ctx).await;
+    let stablecoin_mint = create_mint(ctx, &token_program, 6, transfer_fee_bps).await;
+    let stablecoin_vault = create_token_account(ctx, &market.signer, &stablecoin_mint, &token_program).await;
+    let (currency, nonce) = currency_address(&market.key, &stablecoin_mint);
+
+    let add_currency = ix(
+        accounts::AddCurrency {
+            nft_collaterized_loans: market.key,
+            currency,
+            stablecoin_mint,
+            stablecoin_vault,
+            authority: market.authority.pubkey(),
+            signer: market.signer,
+            system_program: system_program::ID,
+        },
+        instruction::AddCurrency { nonce },
+    );
+    process(ctx, &[add_currency], &[&market.authority]).await.unwrap();
+
+    let borrower = funded_keypair(ctx, 1_000_000_000).await;
+    let borrower_stablecoin_vault = create_token_account(ctx, &borrower.pubkey(), &stablecoin_mint, &token_program).await;
+    mint_to(ctx, &stablecoin_mint, &borrower_stablecoin_vault, 10 * REQUEST_AMOUNT, &token_program).await;
+    let (nft_mint, user_nft_vault) = create_nft(ctx, &borrower.pubkey(), &nft_token_program).await;
+    let nft_vault = create_token_account(ctx, &market.signer, &nft_mint, &nft_token_program).await;
+    let (order, nonce) = order_address(&market.key, 0);
+
+    let create_order = ix(
+        accounts::CreateOrder {
+            nft_collaterized_loans: market.key,
+            currency,
+            stablecoin_mint,
+            stablecoin_vault,
+            user_stablecoin_vault: borrower_stablecoin_vault,
+            nft_mint,
+            nft_vault,
+            user_nft_vault,
+            order,
+            borrower: borrower.pubkey(),
+            signer: market.signer,
+            pnft: no_pnft(),
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+            token_program,
+            nft_token_program,
+        },
+        instruction::CreateOrder {
+            nonce,
+            request_amount: REQUEST_AMOUNT,
+            interest: INTEREST,
+            period: PERIOD,
+            additional_collateral: ADDITIONAL_COLLATERAL,
+            expires_at: 0,
+        },
+    );
+    process(ctx, &[create_order], &[&borrower]).await.unwrap();
+
+    // The escrow holds the full additional collateral whatever the mint withheld.
+    assert_eq!(token_balance(ctx, &stablecoin_vault).await, ADDITIONAL_COLLATERAL);
+    assert_eq!(token_balance(ctx, &nft_vault).await, 1);
+
+    TokenOrder {
+        market,
+        currency,
+        stablecoin_mint,
+        stablecoin_vault,
+        order,
+        borrower,
+        borrower_stablecoin_vault,
+        nft_mint,
+        nft_vault,
+        user_nft_vault,
+        token_program,
+        nft_token_program,
+    }
+}
+
+// Funds the order and returns the lender with the amount it sent.
+async fn give_loan(ctx: &mut ProgramTestContext, token_order: &TokenOrder) -> (Keypair, Pubkey, u64) {
+    let lender = funded_keypair(ctx, 1_000_000_000).await;
+    let lender_stablecoin_vault = create_token_account(ctx, &lender.pubkey(), &token_order.stablecoin_mint, &token_order.token_program).await;
+    mint_to(ctx, &token_order.stablecoin_mint, &lender_stablecoin_vault, 10 * REQUEST_AMOUNT, &token_order.token_program).await;
+    let note_mint = note_mint_address(&token_order.order);
+
+    let give_loan = ix(
+        accounts::GiveLoan {
+            nft_collaterized_loans: token_order.market.key,
+            currency: token_order.currency,
+            order: token_order.order,
+            stablecoin_mint: token_order.stablecoin_mint,
+            stablecoin_vault: token_order.stablecoin_vault,
+            lender_stablecoin_vault,
+            borrower_stablecoin_vault: token_order.borrower_stablecoin_vault,
+            lender: lender.pubkey(),
+            note_mint,
+            lender_note_account: note_account_address(&lender.pubkey(), &note_mint),
+            note_metadata: note_metadata(&note_mint),
+            allowlist: None,
+            signer: token_order.market.signer,
+            instructions_sysvar: None,
+            system_program: system_program::ID,
+            token_program: token_order.token_program,
+            note_token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+        },
+        instruction::GiveLoan { _order_id: 0, request_amount: REQUEST_AMOUNT, interest: INTEREST, period: PERIOD },
+    );
+    let lender_before = token_balance(ctx, &lender_stablecoin_vault).await;
+    let borrower_before = token_balance(ctx, &token_order.borrower_stablecoin_vault).await;
+    process(ctx, &[give_loan], &[&lender]).await.unwrap();
+
+    assert_eq!(token_balance(ctx, &token_order.borrower_stablecoin_vault).await - borrower_before, REQUEST_AMOUNT);
+    assert_eq!(token_balance(ctx, &note_account_address(&lender.pubkey(), &note_mint)).await, 1);
+
+    let sent = lender_before - token_balance(ctx, &lender_stablecoin_vault).await;
+    (lender, lender_stablecoin_vault, sent)
+}
+
+// Repays the loan and returns the amount the borrower sent.
+async fn payback(ctx: &mut ProgramTestContext, token_order: &TokenOrder, lender: &Keypair, lender_stablecoin_vault: Pubkey) -> u64 {
+    let note_account = note_account_address(&lender.pubkey(), &note_mint_address(&token_order.order));
+
+    let payback = ix(
+        accounts::Payback {
+            nft_collaterized_loans: token_order.market.key,
+            currency: token_order.currency,
+            order: token_order.order,
+            stablecoin_mint: token_order.stablecoin_mint,
+            stablecoin_vault: token_order.stablecoin_vault,
+            lender_stablecoin_vault,
+            user_stablecoin_vault: token_order.borrower_stablecoin_vault,
+            payer_stablecoin_vault: token_order.borrower_stablecoin_vault,
+            nft_mint: token_order.nft_mint,
+            nft_vault: token_order.nft_vault,
+            user_nft_vault: token_order.user_nft_vault,
+            borrower: token_order.borrower.pubkey(),
+            payer: token_order.borrower.pubkey(),
+            signer: token_order.market.signer,
+            pnft: no_pnft(),
+            note_account,
+            note_holder: lender.pubkey(),
+            system_program: system_program::ID,
+            token_program: token_order.token_program,
+            nft_token_program: token_order.nft_token_program,
+        },
+        instruction::Payback { _order_id: 0 },
+    );
+    let lender_before = token_balance(ctx, &lender_stablecoin_vault).await;
+    let borrower_before = token_balance(ctx, &token_order.borrower_stablecoin_vault).await;
+    process(ctx, &[payback], &[&token_order.borrower]).await.unwrap();
+
+    assert_eq!(token_balance(ctx, &lender_stablecoin_vault).await - lender_before, REQUEST_AMOUNT + INTEREST);
+    assert_eq!(token_balance(ctx, &token_order.stablecoin_vault).await, 0);
+    assert_eq!(token_balance(ctx, &token_order.user_nft_vault).await, 1);
+    assert_eq!(token_balance(ctx, &token_order.nft_vault).await, 0);
+
+    borrower_before - token_balance(ctx, &token_order.borrower_stablecoin_vault).await
+}
+
+async fn loan_round_trip(token_program: Pubkey, nft_token_program: Pubkey) {
+    let mut ctx = start().await;
+    let token_order = create_order(&mut ctx, token_program, nft_token_program, None).await;
+    let (lender, lender_stablecoin_vault, sent) = give_loan(&mut ctx, &token_order).await;
+    assert_eq!(sent, REQUEST_AMOUNT);
+
+    let repaid = payback(&mut ctx, &token_order, &lender, lender_stablecoin_vault).await;
+    assert_eq!(repaid, REQUEST_AMOUNT + INTEREST - ADDITIONAL_COLLATERAL);
+}
+
+#[tokio::test]
+async fn loan_round_trip_under_token() {
+    loan_round_trip(spl_token::ID, spl_token::ID).await;
+}
+
+#[tokio::test]
+async fn loan_round_trip_under_token_2022() {
+    loan_round_trip(spl_token_2022::ID, spl_token_2022::ID).await;
+}
+
+#[tokio::test]
+async fn loan_round_trip_with_token_2022_collateral() {
+    loan_round_trip(spl_token::ID, spl_token_2022::ID).await;
+}
+
+#[tokio::test]
+async fn transfer_fee_is_paid_on_top_of_the_loan() {
+    let mut ctx = start().await;
+    let token_order = create_order(&mut ctx, spl_token_2022::ID, spl_token_2022::ID, Some(100)).await;
+
+    // The borrower receives the full request, the lender covers the fee.
+    let (lender, lender_stablecoin_vault, sent) = give_loan(&mut ctx, &token_order).await;
+    let fee = transfer_fee(&mut ctx, &token_order.stablecoin_mint, sent).await;
+    assert!(fee > 0);
+    assert_eq!(sent - fee, REQUEST_AMOUNT);
+
+    // The lender receives the full repayment, the borrower covers the fee.
+    let repaid = payback(&mut ctx, &token_order, &lender, lender_stablecoin_vault).await;
+    let returned = ADDITIONAL_COLLATERAL - transfer_fee(&mut ctx, &token_order.stablecoin_mint, ADDITIONAL_COLLATERAL).await;
+    let sent = repaid + returned;
+    assert_eq!(sent - transfer_fee(&mut ctx, &token_order.stablecoin_mint, sent).await, REQUEST_AMOUNT + INTEREST);
+}